@@ -7,6 +7,30 @@ use syn::{
     parse_macro_input, parse_quote, punctuated::Punctuated, ImplItemFn, Item, ItemFn, LitStr, Stmt,
     Token, TraitItemFn,
 };
+
+/// Build the marker attribute the lint looks for.
+///
+/// By default this is an exact doc marker (`#[doc = "rt:realtime"]`,
+/// `#[doc = "rt:non_realtime:<reason>"]`). With the `tool-attrs` feature it is a
+/// tool attribute instead (`#[rt::realtime]`, `#[rt::non_realtime = "<reason>"]`),
+/// which keeps rustdoc output clean but requires
+/// `#![feature(register_tool)] #![register_tool(rt)]` in the annotated crate.
+fn marker_attr(kind: &str, reason: Option<&str>) -> syn::Attribute {
+    if cfg!(feature = "tool-attrs") {
+        let kind = syn::Ident::new(kind, proc_macro2::Span::call_site());
+        match reason {
+            Some(reason) => parse_quote!(#[rt::#kind = #reason]),
+            None => parse_quote!(#[rt::#kind]),
+        }
+    } else {
+        let marker = match reason {
+            Some(reason) => format!("rt:{}:{}", kind, reason),
+            None => format!("rt:{}", kind),
+        };
+        parse_quote!(#[doc = #marker])
+    }
+}
+
 #[proc_macro_attribute]
 pub fn realtime(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Explicitly ignore unused attribute arguments (allow empty args)
@@ -19,50 +43,80 @@ pub fn realtime(attr: TokenStream, item: TokenStream) -> TokenStream {
     //     #[doc = "rt:realtime"]
     //     #vis #sig #block
     // }.into()
+    let marker = marker_attr("realtime", None);
     // 1) Methods in traits
     if let Ok(mut m) = syn::parse::<TraitItemFn>(item.clone()) {
-        m.attrs.push(syn::parse_quote!(#[doc = "rt:realtime"]));
+        m.attrs.push(marker);
         return quote!(#m).into();
     }
     // 2) Methods in impl blocks
     if let Ok(mut m) = syn::parse::<ImplItemFn>(item.clone()) {
-        m.attrs.push(syn::parse_quote!(#[doc = "rt:realtime"]));
+        m.attrs.push(marker);
         return quote!(#m).into();
     }
     // 3) Free functions
     if let Ok(mut f) = syn::parse::<ItemFn>(item.clone()) {
-        f.attrs.push(syn::parse_quote!(#[doc = "rt:realtime"]));
+        f.attrs.push(marker);
         return quote!(#f).into();
     }
     // Return as-is elsewhere (or error out)
     item
 }
 
+/// Reasons accepted by `#[non_realtime("...")]`
+const NON_REALTIME_REASONS: &[&str] = &[
+    "alloc",
+    "io",
+    "file_io",
+    "network",
+    "database",
+    "lock",
+    "blocking",
+    "syscall",
+    "complex_calc",
+];
+
+/// `#[non_realtime]` or `#[non_realtime("<reason>")]`, the reason being one of
+/// [`NON_REALTIME_REASONS`]. The reason is kept in the marker (see
+/// [`marker_attr`]) so the lint can report it.
 #[proc_macro_attribute]
 pub fn non_realtime(attr: TokenStream, item: TokenStream) -> TokenStream {
-    // Explicitly ignore unused attribute arguments (allow empty args)
-    let _ = attr;
-    // let _ = parse_macro_input!(attr as syn::parse::Nothing); // 允许空参数（不接收任何参数）
-    // let f = parse_macro_input!(item as ItemFn);
-    // let vis = &f.vis; let sig = &f.sig; let block = &f.block; let attrs = &f.attrs;
-    // quote! {
-    //     #(#attrs)*
-    //     #[doc = "rt:realtime"]
-    //     #vis #sig #block
-    // }.into()
+    // Allow empty args, or a single string literal naming the reason
+    let reason = if attr.is_empty() {
+        None
+    } else {
+        let reason = parse_macro_input!(attr as LitStr);
+        if !NON_REALTIME_REASONS.contains(&reason.value().as_str()) {
+            // Keep the item so the error is not followed by unresolved-name errors
+            let mut out: TokenStream = syn::Error::new(
+                reason.span(),
+                format!(
+                    "unknown non_realtime reason \"{}\", expected one of: {}",
+                    reason.value(),
+                    NON_REALTIME_REASONS.join(", ")
+                ),
+            )
+            .to_compile_error()
+            .into();
+            out.extend(item);
+            return out;
+        }
+        Some(reason.value())
+    };
+    let marker = marker_attr("non_realtime", reason.as_deref());
     // 1) Methods in traits
     if let Ok(mut m) = syn::parse::<TraitItemFn>(item.clone()) {
-        m.attrs.push(syn::parse_quote!(#[doc = "rt:non_realtime"]));
+        m.attrs.push(marker);
         return quote!(#m).into();
     }
     // 2) Methods in impl blocks
     if let Ok(mut m) = syn::parse::<ImplItemFn>(item.clone()) {
-        m.attrs.push(syn::parse_quote!(#[doc = "rt:non_realtime"]));
+        m.attrs.push(marker);
         return quote!(#m).into();
     }
     // 3) Free functions
     if let Ok(mut f) = syn::parse::<ItemFn>(item.clone()) {
-        f.attrs.push(syn::parse_quote!(#[doc = "rt:non_realtime"]));
+        f.attrs.push(marker);
         return quote!(#f).into();
     }
     // Return as-is elsewhere (or error out)
@@ -0,0 +1,91 @@
+// Heap allocation detection in realtime bodies, on MIR so that allocations
+// hidden in std macros (`vec!`, `format!`) and box creation are visible too
+
+use crate::knowledge::{self, inlined_call, Category};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_lint::LateContext;
+use rustc_middle::mir::{Rvalue, SourceInfo, StatementKind, TerminatorKind};
+use rustc_middle::ty::{TyCtxt, TypingEnv};
+use rustc_span::Span;
+
+/// An allocation found in a body: where it happens (at the macro call site for
+/// code expanded from macros) and what allocates
+pub struct Allocation {
+    pub span: Span,
+    pub what: String,
+    pub reason: &'static str,
+}
+
+pub fn find_allocations(cx: &LateContext<'_>, def_id: LocalDefId) -> Vec<Allocation> {
+    let tcx = cx.tcx;
+    if !tcx.is_mir_available(def_id) {
+        return Vec::new();
+    }
+    let body = tcx.optimized_mir(def_id);
+    let typing_env = TypingEnv::post_analysis(tcx, def_id);
+    let mut found: Vec<Allocation> = Vec::new();
+    let mut push = |span: Span, what: String, reason: &'static str| {
+        let span = span.source_callsite();
+        // One report per source location, e.g. `vec![..]` both creates a box and converts it
+        if !found.iter().any(|a| a.span == span) {
+            found.push(Allocation { span, what, reason });
+        }
+    };
+
+    // Inlined code counts as the call of the inlined function, which may
+    // allocate itself (`Box::new`): `Some` for inlined code, with the call if
+    // it allocates
+    let inlined = |source_info: &SourceInfo| {
+        inlined_call(body, source_info.scope).map(|(instance, call_span)| {
+            allocating_fn(tcx, instance.def_id()).map(|(path, reason)| (call_span, path, reason))
+        })
+    };
+
+    // Cleanup blocks run while unwinding, after the allocations of the normal path
+    for block in body.basic_blocks.iter().filter(|block| !block.is_cleanup) {
+        // 1) Box creation without a call (`box_new` lowered to `ShallowInitBox`)
+        for stmt in &block.statements {
+            if let Some(call) = inlined(&stmt.source_info) {
+                if let Some((span, path, reason)) = call {
+                    push(span, format!("`{}`", path), reason);
+                }
+                continue;
+            }
+            if let StatementKind::Assign(assign) = &stmt.kind {
+                if let Rvalue::ShallowInitBox(_, ty) = &assign.1 {
+                    push(
+                        stmt.source_info.span,
+                        format!("`Box<{}>` creation", ty),
+                        "heap allocation",
+                    );
+                }
+            }
+        }
+        // 2) Calls of known allocating functions
+        let terminator = block.terminator();
+        if let Some(call) = inlined(&terminator.source_info) {
+            if let Some((span, path, reason)) = call {
+                push(span, format!("`{}`", path), reason);
+            }
+            continue;
+        }
+        if let TerminatorKind::Call { func, fn_span, .. } = &terminator.kind {
+            if let Some((callee, args)) = func.const_fn_def() {
+                let callee = knowledge::resolve_callee(tcx, typing_env, callee, args);
+                if let Some((path, reason)) = allocating_fn(tcx, callee) {
+                    push(*fn_span, format!("`{}`", path), reason);
+                }
+            }
+        }
+    }
+    found.sort_by_key(|a| a.span.lo());
+    found
+}
+
+/// Canonical path of `callee` and why it allocates, if the built-in table
+/// says it does
+fn allocating_fn(tcx: TyCtxt<'_>, callee: DefId) -> Option<(String, &'static str)> {
+    let path = knowledge::canonical_path(tcx, callee);
+    let api = knowledge::lookup(&path).filter(|api| api.category == Category::Alloc)?;
+    Some((path, api.reason))
+}
@@ -0,0 +1,197 @@
+// Whole-crate call graph used by the transitive realtime analysis
+
+use crate::implicit;
+use crate::knowledge;
+use rustc_hir as hir;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::ty::{self, GenericArgsRef, TyCtxt, TypeckResults, TypingEnv};
+use rustc_span::Span;
+use std::collections::{HashMap, HashSet};
+
+/// A call (or closure creation) found in a function body. The span is the
+/// macro call site for calls expanded from macros.
+#[derive(Clone, Copy, Debug)]
+pub struct CallSite {
+    pub callee: DefId,
+    pub span: Span,
+}
+
+/// A closure passed as an argument of a call: its body runs wherever the
+/// callee calls it
+#[derive(Clone, Copy, Debug)]
+pub struct ClosureArg {
+    pub closure: LocalDefId,
+    pub callee: DefId,
+}
+
+/// Callees of every local body owner (fns, methods, closures)
+#[derive(Default)]
+pub struct CallGraph {
+    calls: HashMap<LocalDefId, Vec<CallSite>>,
+    closure_args: Vec<ClosureArg>,
+    /// Bodies calling through fn pointers or generic callables, whose callees
+    /// are not in the graph
+    indirect: HashSet<LocalDefId>,
+}
+
+impl CallGraph {
+    pub fn build(tcx: TyCtxt<'_>) -> Self {
+        let mut calls = HashMap::new();
+        let mut closure_args = Vec::new();
+        let mut indirect = HashSet::new();
+        for owner in tcx.hir_body_owners() {
+            let body = tcx.hir_body_owned_by(owner);
+            let mut collector = CallCollector {
+                tcx,
+                typing_env: TypingEnv::post_analysis(tcx, owner),
+                typeck: tcx.typeck(owner),
+                calls: Vec::new(),
+                closure_args: Vec::new(),
+                indirect: false,
+            };
+            collector.visit_body(body);
+            // Calls without a call expression (operators, `?`, ...) from the MIR
+            for call in implicit::find_implicit_calls(tcx, owner) {
+                let site = CallSite {
+                    callee: call.callee,
+                    span: call.span,
+                };
+                if !collector
+                    .calls
+                    .iter()
+                    .any(|known| known.callee == site.callee && known.span == site.span)
+                {
+                    collector.calls.push(site);
+                }
+            }
+            calls.insert(owner, collector.calls);
+            closure_args.extend(collector.closure_args);
+            if collector.indirect {
+                indirect.insert(owner);
+            }
+        }
+        Self {
+            calls,
+            closure_args,
+            indirect,
+        }
+    }
+
+    /// Every body of the graph
+    pub fn bodies(&self) -> impl Iterator<Item = LocalDefId> + '_ {
+        self.calls.keys().copied()
+    }
+
+    /// Whether the body of `def_id` is part of the graph
+    pub fn contains(&self, def_id: LocalDefId) -> bool {
+        self.calls.contains_key(&def_id)
+    }
+
+    pub fn calls_of(&self, def_id: LocalDefId) -> &[CallSite] {
+        self.calls
+            .get(&def_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Closures passed as call arguments, anywhere in the crate
+    pub fn closure_args(&self) -> &[ClosureArg] {
+        &self.closure_args
+    }
+
+    /// Whether the body calls something the graph cannot name
+    pub fn calls_indirectly(&self, def_id: LocalDefId) -> bool {
+        self.indirect.contains(&def_id)
+    }
+}
+
+/// Collects calls of one body. Nested closure bodies are owners of their own,
+/// so a closure expression is recorded as an edge to the closure instead.
+struct CallCollector<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    typing_env: TypingEnv<'tcx>,
+    typeck: &'tcx TypeckResults<'tcx>,
+    calls: Vec<CallSite>,
+    closure_args: Vec<ClosureArg>,
+    indirect: bool,
+}
+
+impl<'tcx> CallCollector<'tcx> {
+    /// Record a call of `callee` with generic arguments `generics`. A trait
+    /// method called on a concrete type is recorded along with the
+    /// implementation it runs, so either one can be classified.
+    fn record_call(
+        &mut self,
+        callee: DefId,
+        generics: GenericArgsRef<'tcx>,
+        span: Span,
+        args: &[hir::Expr<'_>],
+    ) {
+        self.calls.push(CallSite {
+            callee,
+            span: span.source_callsite(),
+        });
+        if self.tcx.trait_of_item(callee).is_some() {
+            let resolved = knowledge::resolve_callee(self.tcx, self.typing_env, callee, generics);
+            if resolved != callee {
+                self.calls.push(CallSite {
+                    callee: resolved,
+                    span: span.source_callsite(),
+                });
+            }
+        }
+        for arg in args {
+            // `f(|x| ..)` as well as `f(&c)` with `let c = |x| ..;`
+            if let ty::Closure(closure, _) = self.typeck.expr_ty(arg).peel_refs().kind() {
+                if let Some(closure) = closure.as_local() {
+                    self.closure_args.push(ClosureArg { closure, callee });
+                }
+            }
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for CallCollector<'tcx> {
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        match expr.kind {
+            // 1) Method call: foo.bar(...)
+            hir::ExprKind::MethodCall(_seg, _recv, args, _fn_span) => {
+                if let Some(def_id) = self.typeck.type_dependent_def_id(expr.hir_id) {
+                    let generics = self.typeck.node_args(expr.hir_id);
+                    self.record_call(def_id, generics, expr.span, args);
+                }
+            }
+            // 2) Ordinary function call: path_fn(...)
+            hir::ExprKind::Call(callee, args) => {
+                let resolved = match callee.kind {
+                    hir::ExprKind::Path(qpath) => {
+                        self.typeck.qpath_res(&qpath, callee.hir_id).opt_def_id()
+                    }
+                    _ => None,
+                };
+                let generics = self.typeck.node_args(callee.hir_id);
+                match (resolved, self.typeck.expr_ty(callee).kind()) {
+                    (Some(def_id), _) => self.record_call(def_id, generics, expr.span, args),
+                    // A variable holding a fn item: `let f = helper; f()`
+                    (None, ty::FnDef(def_id, generics)) => {
+                        self.record_call(*def_id, generics, expr.span, args)
+                    }
+                    // Closure bodies are edges of the function creating them
+                    (None, ty::Closure(..)) => {}
+                    // Fn pointers and generic `F: Fn()` callables
+                    (None, _) => self.indirect = true,
+                }
+            }
+            // 3) Closure: its body runs on behalf of this function
+            hir::ExprKind::Closure(closure) => {
+                self.calls.push(CallSite {
+                    callee: closure.def_id.to_def_id(),
+                    span: expr.span.source_callsite(),
+                });
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
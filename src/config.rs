@@ -0,0 +1,84 @@
+// Lint configuration, read from the `[mylints]` table of dylint.toml
+
+use crate::knowledge;
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Classification of external functions that cannot carry markers, e.g.
+    /// `{ path = "lock_api::Mutex::lock", class = "non_realtime", reason = "lock" }`
+    pub external: Vec<ExternalRule>,
+    /// File to append a JSON-lines report of the violations to, overridden by
+    /// the `RT_LINT_JSON` environment variable
+    pub json_report: Option<String>,
+    /// File to write a SARIF 2.1.0 log of the violations to, `{crate}` being
+    /// replaced by the crate name; overridden by `RT_LINT_SARIF`
+    pub sarif_report: Option<String>,
+    /// File to write the inferred realtime safety of unannotated local
+    /// functions to, as JSON lines; overridden by `RT_LINT_INFERRED`
+    pub inferred_dump: Option<String>,
+    /// Also follow the MIR of realtime functions through the concrete instances
+    /// of generic and trait calls (`T::process(&x)`), reporting the
+    /// substitution that leads to a non-realtime function
+    pub monomorphize: bool,
+    /// Whether closures defined in realtime code are realtime themselves
+    pub closure_policy: ClosurePolicy,
+    /// Report calls from realtime code to functions that are neither marked nor
+    /// classified (REALTIME_CALLS_UNANNOTATED)
+    pub strict: bool,
+    /// Canonical paths or `prefix::*` patterns of functions strict mode lets
+    /// realtime code call unannotated, e.g. `"std::*"`
+    pub strict_allow: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExternalRule {
+    /// Canonical path (`krate::module::Type::method`, without generic arguments,
+    /// type aliases resolved) or a `prefix::*` pattern
+    pub path: String,
+    pub class: Class,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Class {
+    Realtime,
+    NonRealtime,
+}
+
+/// Which closures defined in a realtime function are checked as realtime code.
+/// Closures passed to a realtime function, and closures bound with
+/// `#[rt_call_info("closure", "realtime")]`, are checked under either policy;
+/// closures bound with `#[rt_call_info("closure", "nonrealtime")]` never are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosurePolicy {
+    /// Every closure inherits the context of the function it is defined in
+    #[default]
+    Inherit,
+    /// Only closures that are passed to a realtime function or marked realtime;
+    /// calls of the others are checked through what they do
+    Explicit,
+}
+
+impl Config {
+    pub fn load() -> Self {
+        dylint_linting::config_or_default(env!("CARGO_PKG_NAME"))
+    }
+
+    /// First rule matching the canonical path of an external function
+    pub fn external_rule(&self, path: &str) -> Option<&ExternalRule> {
+        self.external
+            .iter()
+            .find(|rule| knowledge::path_matches(&rule.path, path))
+    }
+
+    /// Whether strict mode allows calling the unannotated function at `path`
+    pub fn strict_allows(&self, path: &str) -> bool {
+        self.strict_allow
+            .iter()
+            .any(|pattern| knowledge::path_matches(pattern, path))
+    }
+}
@@ -0,0 +1,38 @@
+// Trait-object dispatch: which functions a `dyn Trait` method call may run
+
+use rustc_hir as hir;
+use rustc_hir::def_id::DefId;
+use rustc_lint::LateContext;
+use rustc_middle::ty::{self, TyCtxt};
+
+/// The trait of a method call made on a trait object, e.g. `Shape` for
+/// `s.area()` with `s: &dyn Shape` or `Box<dyn Shape>`
+pub fn dyn_trait_of_call(
+    cx: &LateContext<'_>,
+    recv: &hir::Expr<'_>,
+    method: DefId,
+) -> Option<DefId> {
+    let trait_id = cx.tcx.trait_of_item(method)?;
+    // The adjusted receiver is the one passed to the method, after auto-deref
+    let recv_ty = cx.typeck_results().expr_ty_adjusted(recv).peel_refs();
+    match recv_ty.kind() {
+        ty::Dynamic(..) => Some(trait_id),
+        _ => None,
+    }
+}
+
+/// Every function a dynamic call of the trait method `method` may dispatch to:
+/// the implementing item of each impl of the trait in the crate graph, or the
+/// trait's default body for impls that do not override it
+pub fn implementations(tcx: TyCtxt<'_>, trait_id: DefId, method: DefId) -> Vec<DefId> {
+    let has_default = tcx.defaultness(method).has_value();
+    let mut found = Vec::new();
+    for impl_id in tcx.all_impls(trait_id) {
+        match tcx.impl_item_implementor_ids(impl_id).get(&method) {
+            Some(item) => found.push(*item),
+            None if has_default && !found.contains(&method) => found.push(method),
+            None => {}
+        }
+    }
+    found
+}
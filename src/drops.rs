@@ -0,0 +1,188 @@
+// Implicit destructor calls in realtime bodies: values going out of scope run
+// their drop glue, which never shows up as a call in HIR, so the `Drop`
+// terminators of the MIR are inspected instead
+
+use crate::knowledge::{self, inlined_call};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_lint::LateContext;
+use rustc_middle::mir::{TerminatorKind, VarDebugInfoContents};
+use rustc_middle::ty::{self, Ty, TyCtxt, TypingEnv};
+use rustc_span::Span;
+use std::collections::HashSet;
+
+/// Crates whose destructors free heap memory (the std collections)
+const DEALLOCATING_CRATES: &[&str] = &["alloc", "hashbrown"];
+
+/// Something the drop glue of a type runs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DropCause {
+    /// Frees heap memory, e.g. the buffer of a `Vec`; holds the type owning it
+    Dealloc(String),
+    /// Calls a user `Drop::drop`
+    Destructor(DefId),
+}
+
+/// A value dropped in a body: where (at the macro call site for code expanded
+/// from macros), its type, the variable it is bound to if any, and what its
+/// drop glue runs
+pub struct DropSite {
+    pub span: Span,
+    pub ty: String,
+    pub var: Option<String>,
+    pub causes: Vec<DropCause>,
+}
+
+pub fn find_drops(cx: &LateContext<'_>, def_id: LocalDefId) -> Vec<DropSite> {
+    let tcx = cx.tcx;
+    if !tcx.is_mir_available(def_id) {
+        return Vec::new();
+    }
+    let body = tcx.optimized_mir(def_id);
+    let typing_env = TypingEnv::post_analysis(tcx, def_id);
+    let mut found: Vec<DropSite> = Vec::new();
+
+    // Cleanup blocks only drop again what the normal path drops
+    for block in body.basic_blocks.iter().filter(|block| !block.is_cleanup) {
+        let terminator = block.terminator();
+        // Of an inlined call, only what an inlined `drop(x)` drops counts: `x`
+        if let Some((instance, call_span)) = inlined_call(body, terminator.source_info.scope) {
+            if !matches!(terminator.kind, TerminatorKind::Drop { .. })
+                || knowledge::canonical_path(tcx, instance.def_id()) != "std::mem::drop"
+            {
+                continue;
+            }
+            let ty = instance.args.type_at(0);
+            push_site(tcx, typing_env, &mut found, call_span, ty, None);
+            continue;
+        }
+        // 1) Drop glue of values going out of scope, 2) `drop(x)`
+        let (place, ty, span) = match &terminator.kind {
+            TerminatorKind::Drop { place, .. } => (
+                Some(*place),
+                place.ty(body, tcx).ty,
+                terminator.source_info.span,
+            ),
+            TerminatorKind::Call {
+                func,
+                args,
+                fn_span,
+                ..
+            } => {
+                let Some((callee, generics)) = func.const_fn_def() else {
+                    continue;
+                };
+                if knowledge::canonical_path(tcx, callee) != "std::mem::drop" {
+                    continue;
+                }
+                // Zero-sized values are passed as constants
+                let place = args.first().and_then(|arg| arg.node.place());
+                (place, generics.type_at(0), *fn_span)
+            }
+            _ => continue,
+        };
+        let var = place.and_then(|place| place.as_local()).and_then(|local| {
+            body.var_debug_info
+                .iter()
+                .find_map(|info| match &info.value {
+                    VarDebugInfoContents::Place(p) if p.as_local() == Some(local) => {
+                        Some(info.name.to_string())
+                    }
+                    _ => None,
+                })
+        });
+        push_site(tcx, typing_env, &mut found, span, ty, var);
+    }
+    found.sort_by_key(|site| site.span.lo());
+    found
+}
+
+/// Add the drop of a `ty` at `span` to `found`, if its drop glue runs
+/// anything of interest
+fn push_site<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    typing_env: TypingEnv<'tcx>,
+    found: &mut Vec<DropSite>,
+    span: Span,
+    ty: Ty<'tcx>,
+    var: Option<String>,
+) {
+    let mut causes = Vec::new();
+    drop_causes(tcx, typing_env, ty, &mut HashSet::new(), &mut causes);
+    if causes.is_empty() {
+        return;
+    }
+    let span = span.source_callsite();
+    let ty = ty.to_string();
+    if found
+        .iter()
+        .any(|site| site.span == span && site.var == var && site.ty == ty)
+    {
+        return;
+    }
+    found.push(DropSite {
+        span,
+        ty,
+        var,
+        causes,
+    });
+}
+
+/// What dropping a value of type `ty` runs, outermost first
+fn drop_causes<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    typing_env: TypingEnv<'tcx>,
+    ty: Ty<'tcx>,
+    visited: &mut HashSet<Ty<'tcx>>,
+    causes: &mut Vec<DropCause>,
+) {
+    if !visited.insert(ty) || !ty.needs_drop(tcx, typing_env) {
+        return;
+    }
+    let push = |causes: &mut Vec<DropCause>, cause: DropCause| {
+        if !causes.contains(&cause) {
+            causes.push(cause);
+        }
+    };
+    match ty.kind() {
+        _ if ty.is_box() => {
+            push(causes, DropCause::Dealloc(ty.to_string()));
+            if let Some(boxed) = ty.boxed_ty() {
+                drop_causes(tcx, typing_env, boxed, visited, causes);
+            }
+        }
+        ty::Adt(adt, args) => {
+            match tcx.adt_destructor(adt.did()) {
+                // A std collection: it frees its buffer and drops its elements,
+                // the internals it is made of are not of interest
+                Some(dtor)
+                    if DEALLOCATING_CRATES.contains(&tcx.crate_name(dtor.did.krate).as_str()) =>
+                {
+                    push(causes, DropCause::Dealloc(ty.to_string()));
+                    for arg in args.types() {
+                        drop_causes(tcx, typing_env, arg, visited, causes);
+                    }
+                    return;
+                }
+                Some(dtor) => push(causes, DropCause::Destructor(dtor.did)),
+                None => {}
+            }
+            for field in adt.all_fields() {
+                drop_causes(tcx, typing_env, field.ty(tcx, args), visited, causes);
+            }
+        }
+        ty::Tuple(tys) => {
+            for ty in tys.iter() {
+                drop_causes(tcx, typing_env, ty, visited, causes);
+            }
+        }
+        ty::Array(elem, _) | ty::Slice(elem) => {
+            drop_causes(tcx, typing_env, *elem, visited, causes);
+        }
+        ty::Closure(_, args) => {
+            for ty in args.as_closure().upvar_tys() {
+                drop_causes(tcx, typing_env, ty, visited, causes);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,454 @@
+// Flow of functions into fn-pointer places: crate-wide for struct fields,
+// array elements, statics and `Option<fn()>`s, per body and flow-sensitive for
+// local variables, so that calls through them can be resolved to the functions
+// they may run
+
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, Visitor};
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use std::collections::{HashMap, HashSet};
+
+/// A place a function can be stored in. Places are field- and
+/// element-insensitive: every `S::cb` is one place, and so is every element
+/// of an array.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Place {
+    /// A struct or variant field, by field definition
+    Field(DefId),
+    /// A static or const
+    Static(DefId),
+    /// A local variable, by binding
+    Local(hir::HirId),
+}
+
+/// Something flowing into a place
+#[derive(Clone, Copy, Debug)]
+enum Source {
+    Fn(DefId),
+    Place(Place),
+}
+
+/// Functions that may be stored in each place
+#[derive(Default)]
+pub struct FnPtrFlow {
+    targets: HashMap<Place, HashSet<DefId>>,
+}
+
+impl FnPtrFlow {
+    pub fn build(tcx: TyCtxt<'_>) -> Self {
+        let mut flows = Vec::new();
+        for owner in tcx.hir_body_owners() {
+            let body = tcx.hir_body_owned_by(owner);
+            let typeck = tcx.typeck(owner);
+            // The value of a static or const initializer
+            if matches!(tcx.def_kind(owner), DefKind::Static { .. } | DefKind::Const) {
+                for source in sources(typeck, body.value) {
+                    flows.push((Place::Static(owner.to_def_id()), source));
+                }
+            }
+            let mut collector = FlowCollector {
+                typeck,
+                flows: Vec::new(),
+            };
+            collector.visit_body(body);
+            flows.extend(collector.flows);
+        }
+
+        // Propagate place-to-place flows until nothing changes
+        let mut targets: HashMap<Place, HashSet<DefId>> = HashMap::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (dest, source) in &flows {
+                let new = match source {
+                    Source::Fn(def_id) => vec![*def_id],
+                    Source::Place(place) => targets
+                        .get(place)
+                        .map(|set| set.iter().copied().collect())
+                        .unwrap_or_default(),
+                };
+                let set = targets.entry(*dest).or_default();
+                for def_id in new {
+                    changed |= set.insert(def_id);
+                }
+            }
+        }
+        Self { targets }
+    }
+
+    /// Functions the callee expression of a call through a fn pointer may
+    /// evaluate to, as far as the flow is known
+    pub fn targets_of(&self, typeck: &TypeckResults<'_>, expr: &hir::Expr<'_>) -> Vec<DefId> {
+        let mut found = Vec::new();
+        for source in sources(typeck, expr) {
+            let def_ids = match source {
+                Source::Fn(def_id) => vec![def_id],
+                Source::Place(place) => self
+                    .targets
+                    .get(&place)
+                    .map(|set| set.iter().copied().collect())
+                    .unwrap_or_default(),
+            };
+            for def_id in def_ids {
+                if !found.contains(&def_id) {
+                    found.push(def_id);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Place of an assignable expression: `x`, `s.cb`, `TABLE`, `arr[i]`, `*p`
+fn place_of(typeck: &TypeckResults<'_>, expr: &hir::Expr<'_>) -> Option<Place> {
+    match expr.kind {
+        hir::ExprKind::Path(ref qpath) => match typeck.qpath_res(qpath, expr.hir_id) {
+            Res::Local(hir_id) => Some(Place::Local(hir_id)),
+            Res::Def(DefKind::Static { .. } | DefKind::Const, def_id) => {
+                Some(Place::Static(def_id))
+            }
+            _ => None,
+        },
+        hir::ExprKind::Field(base, _) => {
+            let index = typeck.opt_field_index(expr.hir_id)?;
+            // Field accesses are on structs and unions, which have a single variant
+            let adt = typeck.expr_ty_adjusted(base).peel_refs().ty_adt_def()?;
+            Some(Place::Field(adt.non_enum_variant().fields[index].did))
+        }
+        hir::ExprKind::Index(base, _, _) | hir::ExprKind::Unary(hir::UnOp::Deref, base) => {
+            place_of(typeck, base)
+        }
+        _ => None,
+    }
+}
+
+/// What a value expression may evaluate to, looking through containers
+/// (arrays, `Some(f)`, tuple structs), references, branches and
+/// `unwrap`/`expect`
+fn sources(typeck: &TypeckResults<'_>, expr: &hir::Expr<'_>) -> Vec<Source> {
+    let mut found = Vec::new();
+    collect_sources(typeck, expr, &mut found);
+    found
+}
+
+fn collect_sources(typeck: &TypeckResults<'_>, expr: &hir::Expr<'_>, found: &mut Vec<Source>) {
+    match expr.kind {
+        hir::ExprKind::Path(ref qpath) => match typeck.qpath_res(qpath, expr.hir_id) {
+            Res::Def(DefKind::Fn | DefKind::AssocFn, def_id) => found.push(Source::Fn(def_id)),
+            _ => found.extend(place_of(typeck, expr).map(Source::Place)),
+        },
+        hir::ExprKind::Field(..) | hir::ExprKind::Index(..) => {
+            found.extend(place_of(typeck, expr).map(Source::Place))
+        }
+        hir::ExprKind::Array(elems) | hir::ExprKind::Tup(elems) => {
+            for elem in elems {
+                collect_sources(typeck, elem, found);
+            }
+        }
+        hir::ExprKind::Repeat(elem, _)
+        | hir::ExprKind::AddrOf(_, _, elem)
+        | hir::ExprKind::Unary(hir::UnOp::Deref, elem)
+        | hir::ExprKind::Cast(elem, _)
+        | hir::ExprKind::DropTemps(elem) => collect_sources(typeck, elem, found),
+        // `Some(f)`, `Wrapper(f)`
+        hir::ExprKind::Call(callee, args) => {
+            if let hir::ExprKind::Path(ref qpath) = callee.kind {
+                if let Res::Def(DefKind::Ctor(..), _) = typeck.qpath_res(qpath, callee.hir_id) {
+                    for arg in args {
+                        collect_sources(typeck, arg, found);
+                    }
+                }
+            }
+        }
+        // `opt.unwrap()`, `opt.expect("..")`, `*slot.as_ref().unwrap()`
+        hir::ExprKind::MethodCall(seg, recv, _, _) => {
+            if matches!(
+                seg.ident.as_str(),
+                "unwrap" | "expect" | "unwrap_unchecked" | "as_ref" | "clone" | "copied" | "get"
+            ) {
+                collect_sources(typeck, recv, found);
+            }
+        }
+        hir::ExprKind::Block(block, _) => {
+            if let Some(tail) = block.expr {
+                collect_sources(typeck, tail, found);
+            }
+        }
+        hir::ExprKind::If(_, then, els) => {
+            collect_sources(typeck, then, found);
+            if let Some(els) = els {
+                collect_sources(typeck, els, found);
+            }
+        }
+        hir::ExprKind::Match(_, arms, _) => {
+            for arm in arms {
+                collect_sources(typeck, arm.body, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects the flows of one body: `let` bindings (including the bindings of
+/// `if let` / `match` patterns), assignments and struct literals
+struct FlowCollector<'tcx> {
+    typeck: &'tcx TypeckResults<'tcx>,
+    flows: Vec<(Place, Source)>,
+}
+
+impl FlowCollector<'_> {
+    /// Every binding of `pat` may hold what `init` evaluates to
+    fn bind(&mut self, pat: &hir::Pat<'_>, init: &hir::Expr<'_>) {
+        let sources = sources(self.typeck, init);
+        if sources.is_empty() {
+            return;
+        }
+        pat.each_binding(|_, hir_id, _, _| {
+            for source in &sources {
+                self.flows.push((Place::Local(hir_id), *source));
+            }
+        });
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for FlowCollector<'tcx> {
+    fn visit_local(&mut self, local: &'tcx hir::LetStmt<'tcx>) {
+        if let Some(init) = local.init {
+            self.bind(local.pat, init);
+        }
+        intravisit::walk_local(self, local);
+    }
+
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        match expr.kind {
+            // `if let Some(cb) = self.cb`
+            hir::ExprKind::Let(let_expr) => self.bind(let_expr.pat, let_expr.init),
+            hir::ExprKind::Match(scrutinee, arms, _) => {
+                for arm in arms {
+                    self.bind(arm.pat, scrutinee);
+                }
+            }
+            // `self.cb = Some(f)`, `table[i] = f`
+            hir::ExprKind::Assign(lhs, rhs, _) => {
+                if let Some(place) = place_of(self.typeck, lhs) {
+                    for source in sources(self.typeck, rhs) {
+                        self.flows.push((place, source));
+                    }
+                }
+            }
+            // `S { cb: f, .. }`
+            hir::ExprKind::Struct(qpath, fields, _) => {
+                if let Some(adt) = self.typeck.expr_ty(expr).ty_adt_def() {
+                    let variant = adt.variant_of_res(self.typeck.qpath_res(qpath, expr.hir_id));
+                    for field in fields {
+                        let Some(index) = self.typeck.opt_field_index(field.hir_id) else {
+                            continue;
+                        };
+                        let place = Place::Field(variant.fields[index].did);
+                        for source in sources(self.typeck, field.expr) {
+                            self.flows.push((place, source));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        intravisit::walk_expr(self, expr);
+    }
+}
+
+/// Functions each fn-pointer variable may hold at a point of a body, keyed by
+/// binding. Variables missing from the state hold something unknown here
+/// (e.g. a parameter), for which the crate-wide flow is used instead.
+type LocalState = HashMap<hir::HirId, HashSet<DefId>>;
+
+impl FnPtrFlow {
+    /// Flow-sensitive targets of the calls through fn-pointer variables of one
+    /// body, keyed by call expression. Bindings are told apart by `HirId`, so
+    /// shadowing is precise; an assignment replaces what the variable held,
+    /// and after a branch a variable holds what it held at the end of any arm.
+    pub fn local_targets<'tcx>(
+        &self,
+        typeck: &TypeckResults<'tcx>,
+        body: &hir::Body<'tcx>,
+    ) -> HashMap<hir::HirId, Vec<DefId>> {
+        let mut analysis = LocalFlow {
+            flow: self,
+            typeck,
+            state: LocalState::new(),
+            calls: HashMap::new(),
+        };
+        analysis.eval(body.value);
+        analysis.calls
+    }
+}
+
+struct LocalFlow<'a, 'tcx> {
+    flow: &'a FnPtrFlow,
+    typeck: &'a TypeckResults<'tcx>,
+    state: LocalState,
+    calls: HashMap<hir::HirId, Vec<DefId>>,
+}
+
+impl<'tcx> LocalFlow<'_, 'tcx> {
+    /// Functions a value expression may evaluate to in the current state, or
+    /// `None` if it is not a function
+    fn value(&self, expr: &hir::Expr<'_>) -> Option<HashSet<DefId>> {
+        let mut found = HashSet::new();
+        for source in sources(self.typeck, expr) {
+            match source {
+                Source::Fn(def_id) => {
+                    found.insert(def_id);
+                }
+                Source::Place(Place::Local(hir_id)) if self.state.contains_key(&hir_id) => {
+                    found.extend(self.state[&hir_id].iter().copied());
+                }
+                Source::Place(place) => {
+                    found.extend(self.flow.targets.get(&place).into_iter().flatten().copied());
+                }
+            }
+        }
+        (!found.is_empty()).then_some(found)
+    }
+
+    fn bind(&mut self, pat: &hir::Pat<'_>, init: Option<&hir::Expr<'_>>) {
+        let value = init.and_then(|init| self.value(init));
+        pat.each_binding(|_, hir_id, _, _| match &value {
+            Some(value) => {
+                self.state.insert(hir_id, value.clone());
+            }
+            None => {
+                self.state.remove(&hir_id);
+            }
+        });
+    }
+
+    /// Evaluate `expr`, updating the state with its assignments and recording
+    /// its calls through fn-pointer variables
+    fn eval(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        match expr.kind {
+            hir::ExprKind::Block(block, _) => self.eval_block(block),
+            hir::ExprKind::If(cond, then, els) => {
+                self.eval(cond);
+                let before = self.state.clone();
+                self.eval(then);
+                let after_then = std::mem::replace(&mut self.state, before);
+                if let Some(els) = els {
+                    self.eval(els);
+                }
+                join(&mut self.state, after_then);
+            }
+            hir::ExprKind::Match(scrutinee, arms, _) => {
+                self.eval(scrutinee);
+                let before = self.state.clone();
+                let mut after = None;
+                for arm in arms {
+                    self.state = before.clone();
+                    self.bind(arm.pat, Some(scrutinee));
+                    if let Some(guard) = arm.guard {
+                        self.eval(guard);
+                    }
+                    self.eval(arm.body);
+                    match &mut after {
+                        None => after = Some(self.state.clone()),
+                        Some(after) => join(after, self.state.clone()),
+                    }
+                }
+                self.state = after.unwrap_or(before);
+            }
+            // Run the body until the state at its entry no longer grows
+            hir::ExprKind::Loop(block, ..) => loop {
+                let entry = self.state.clone();
+                self.eval_block(block);
+                join(&mut self.state, entry.clone());
+                if self.state == entry {
+                    break;
+                }
+            },
+            hir::ExprKind::Let(let_expr) => {
+                self.eval(let_expr.init);
+                self.bind(let_expr.pat, Some(let_expr.init));
+            }
+            hir::ExprKind::Assign(lhs, rhs, _) => {
+                self.eval(rhs);
+                match lhs.kind {
+                    hir::ExprKind::Path(ref qpath) => {
+                        if let Res::Local(hir_id) = self.typeck.qpath_res(qpath, lhs.hir_id) {
+                            match self.value(rhs) {
+                                Some(value) => self.state.insert(hir_id, value),
+                                None => self.state.remove(&hir_id),
+                            };
+                        }
+                    }
+                    _ => self.eval(lhs),
+                }
+            }
+            hir::ExprKind::Call(callee, args) => {
+                self.eval(callee);
+                for arg in args {
+                    self.eval(arg);
+                }
+                if let hir::ExprKind::Path(ref qpath) = callee.kind {
+                    if let Res::Local(hir_id) = self.typeck.qpath_res(qpath, callee.hir_id) {
+                        if let Some(targets) = self.state.get(&hir_id) {
+                            self.calls
+                                .insert(expr.hir_id, targets.iter().copied().collect());
+                        }
+                    }
+                }
+            }
+            // Closure bodies are analyzed on their own
+            hir::ExprKind::Closure(..) => {}
+            _ => {
+                let mut children = Children(Vec::new());
+                intravisit::walk_expr(&mut children, expr);
+                for child in children.0 {
+                    self.eval(child);
+                }
+            }
+        }
+    }
+
+    fn eval_block(&mut self, block: &'tcx hir::Block<'tcx>) {
+        for stmt in block.stmts {
+            match stmt.kind {
+                hir::StmtKind::Let(local) => {
+                    if let Some(init) = local.init {
+                        self.eval(init);
+                    }
+                    if let Some(els) = local.els {
+                        self.eval_block(els);
+                    }
+                    self.bind(local.pat, local.init);
+                }
+                hir::StmtKind::Expr(expr) | hir::StmtKind::Semi(expr) => self.eval(expr),
+                hir::StmtKind::Item(_) => {}
+            }
+        }
+        if let Some(tail) = block.expr {
+            self.eval(tail);
+        }
+    }
+}
+
+/// After a branch, a variable may hold what it holds at the end of either arm;
+/// a variable unknown in one arm stays unknown
+fn join(state: &mut LocalState, other: LocalState) {
+    state.retain(|hir_id, _| other.contains_key(hir_id));
+    for (hir_id, targets) in other {
+        if let Some(set) = state.get_mut(&hir_id) {
+            set.extend(targets);
+        }
+    }
+}
+
+/// Direct subexpressions of an expression, in evaluation order
+struct Children<'tcx>(Vec<&'tcx hir::Expr<'tcx>>);
+
+impl<'tcx> Visitor<'tcx> for Children<'tcx> {
+    fn visit_expr(&mut self, expr: &'tcx hir::Expr<'tcx>) {
+        self.0.push(expr);
+    }
+}
@@ -0,0 +1,170 @@
+// Calls the compiler inserts without a call expression in the source:
+// overloaded operators, `Deref` coercions, indexing, `?` and `for` loops. The
+// HIR pass only sees call expressions, so they are taken from the `Call`
+// terminators of the MIR
+
+use crate::knowledge;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_hir::LangItem;
+use rustc_middle::mir::{CallSource, TerminatorKind};
+use rustc_middle::ty::{self, GenericArgsRef, Ty, TyCtxt, TypingEnv};
+use rustc_span::{sym, DesugaringKind, Span};
+
+/// What inserts an implicit call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImplicitKind {
+    /// `a + b`, `-a`, `a += b`, `a == b`, ... on a type overloading the operator
+    Operator,
+    /// `*a`, and the coercions and auto-derefs of `Deref` / `DerefMut`
+    Deref,
+    /// `a[i]` through `Index` / `IndexMut`
+    Index,
+    /// `?`: `Try::branch`, `FromResidual::from_residual` and the `From`
+    /// conversion of the error
+    QuestionMark,
+    /// `for`: `IntoIterator::into_iter` and `Iterator::next`
+    ForLoop,
+}
+
+impl ImplicitKind {
+    pub fn describe(self) -> &'static str {
+        match self {
+            ImplicitKind::Operator => "an overloaded operator",
+            ImplicitKind::Deref => "a dereference",
+            ImplicitKind::Index => "indexing",
+            ImplicitKind::QuestionMark => "the `?` operator",
+            ImplicitKind::ForLoop => "a `for` loop",
+        }
+    }
+}
+
+/// An implicit call found in a body: where it is (at the macro or desugaring
+/// call site), the function that runs (the implementation when the types are
+/// known) and whether the HIR pass sees the call, as the trait method, at all
+pub struct ImplicitCall {
+    pub span: Span,
+    pub callee: DefId,
+    pub kind: ImplicitKind,
+    pub hir_visible: bool,
+}
+
+pub fn find_implicit_calls(tcx: TyCtxt<'_>, def_id: LocalDefId) -> Vec<ImplicitCall> {
+    // Constants have no optimized MIR
+    if !tcx.def_kind(def_id).is_fn_like() || !tcx.is_mir_available(def_id) {
+        return Vec::new();
+    }
+    let body = tcx.optimized_mir(def_id);
+    let typing_env = TypingEnv::post_analysis(tcx, def_id);
+    let mut found: Vec<ImplicitCall> = Vec::new();
+    let mut push = |span: Span, callee: DefId, kind: ImplicitKind, hir_visible: bool| {
+        let span = span.source_callsite();
+        if !found
+            .iter()
+            .any(|call| call.span == span && call.callee == callee)
+        {
+            found.push(ImplicitCall {
+                span,
+                callee,
+                kind,
+                hir_visible,
+            });
+        }
+    };
+
+    for block in body.basic_blocks.iter().filter(|block| !block.is_cleanup) {
+        let terminator = block.terminator();
+        let TerminatorKind::Call {
+            func,
+            call_source,
+            fn_span,
+            ..
+        } = &terminator.kind
+        else {
+            continue;
+        };
+        // Calls of inlined functions are theirs, checked where they are defined
+        if terminator
+            .source_info
+            .scope
+            .inlined_instance(&body.source_scopes)
+            .is_some()
+        {
+            continue;
+        }
+        let Some((callee, args)) = func.const_fn_def() else {
+            continue;
+        };
+        // 1) Calls without a call expression, 2) calls `?` and `for` are
+        // desugared into, which the HIR pass checks as the trait method only
+        let kind = match (call_source, fn_span.desugaring_kind()) {
+            (CallSource::OverloadedOperator | CallSource::MatchCmp, _) => {
+                operator_kind(tcx, callee)
+            }
+            (_, Some(DesugaringKind::QuestionMark)) => ImplicitKind::QuestionMark,
+            (_, Some(DesugaringKind::ForLoop)) => ImplicitKind::ForLoop,
+            _ => continue,
+        };
+        let hir_visible = *call_source == CallSource::Normal;
+        let resolved = knowledge::resolve_callee(tcx, typing_env, callee, args);
+        if !hir_visible || resolved != callee {
+            push(*fn_span, resolved, kind, hir_visible);
+        }
+        // The error conversion of `?` runs inside `from_residual`
+        if kind == ImplicitKind::QuestionMark
+            && tcx.is_lang_item(callee, LangItem::TryTraitFromResidual)
+        {
+            if let Some(from) = error_conversion(tcx, typing_env, args) {
+                push(*fn_span, from, kind, false);
+            }
+        }
+    }
+    found.sort_by_key(|call| call.span.lo());
+    found
+}
+
+/// Kind of an overloaded operator call by the trait of `callee`
+fn operator_kind(tcx: TyCtxt<'_>, callee: DefId) -> ImplicitKind {
+    let Some(trait_id) = tcx.trait_of_item(callee) else {
+        return ImplicitKind::Operator;
+    };
+    match tcx.as_lang_item(trait_id) {
+        Some(LangItem::Deref | LangItem::DerefMut) => ImplicitKind::Deref,
+        Some(LangItem::Index | LangItem::IndexMut) => ImplicitKind::Index,
+        _ => ImplicitKind::Operator,
+    }
+}
+
+/// `From::from` converting the error of `?` on a `Result` into the error type
+/// of the function, when the two differ. `args` are those of
+/// `<Result<T, F> as FromResidual<Result<Infallible, E>>>::from_residual`.
+fn error_conversion<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    typing_env: TypingEnv<'tcx>,
+    args: GenericArgsRef<'tcx>,
+) -> Option<DefId> {
+    let into = result_error(tcx, args.type_at(0))?;
+    let from = result_error(tcx, args.type_at(1))?;
+    if into == from {
+        return None;
+    }
+    let from_trait = tcx.get_diagnostic_item(sym::From)?;
+    let from_fn = tcx
+        .associated_items(from_trait)
+        .filter_by_name_unhygienic(sym::from)
+        .next()?
+        .def_id;
+    let from_args = tcx.mk_args(&[into.into(), from.into()]);
+    Some(knowledge::resolve_callee(
+        tcx, typing_env, from_fn, from_args,
+    ))
+}
+
+/// `E` of `Result<T, E>`
+fn result_error<'tcx>(tcx: TyCtxt<'tcx>, ty: Ty<'tcx>) -> Option<Ty<'tcx>> {
+    match ty.kind() {
+        ty::Adt(adt, args) if tcx.is_diagnostic_item(sym::Result, adt.did()) => {
+            Some(args.type_at(1))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,248 @@
+// Inferred realtime safety of unannotated local functions, so that helpers
+// doing only realtime work need no marker
+
+use crate::allocations;
+use crate::callgraph::CallGraph;
+use crate::config::Config;
+use crate::drops::{self, DropCause};
+use crate::knowledge::{self, Category};
+use crate::report::{self, Location};
+use crate::Realtime;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_lint::LateContext;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+
+/// Environment variable naming the file the inferred classifications are
+/// dumped to, takes precedence over the `inferred_dump` key of dylint.toml
+pub const INFERRED_DUMP_ENV: &str = "RT_LINT_INFERRED";
+
+/// Inferred status of an unannotated function, with the reason for anything
+/// but `Safe`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inferred {
+    /// Calls only realtime or inferred-safe functions and does not allocate
+    Safe,
+    /// Calls something that is not known either way
+    Unknown(String),
+    /// Calls a non-realtime or inferred-unsafe function, or allocates or
+    /// frees heap memory
+    Unsafe(String),
+}
+
+impl Inferred {
+    fn rank(&self) -> u8 {
+        match self {
+            Inferred::Safe => 0,
+            Inferred::Unknown(_) => 1,
+            Inferred::Unsafe(_) => 2,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Inferred::Safe => "safe",
+            Inferred::Unknown(_) => "unknown",
+            Inferred::Unsafe(_) => "unsafe",
+        }
+    }
+
+    fn reason(&self) -> Option<&str> {
+        match self {
+            Inferred::Safe => None,
+            Inferred::Unknown(reason) | Inferred::Unsafe(reason) => Some(reason),
+        }
+    }
+
+    /// The worse of two statuses, the first one on ties
+    fn max(self, other: Inferred) -> Inferred {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// One line of the dump
+#[derive(Serialize)]
+struct Entry<'a> {
+    function: String,
+    status: &'static str,
+    reason: Option<&'a str>,
+    #[serde(flatten)]
+    location: Location,
+}
+
+/// Inferred status of every unannotated local fn, method and closure
+#[derive(Default)]
+pub struct Inference {
+    status: HashMap<LocalDefId, Inferred>,
+}
+
+impl Inference {
+    /// Infer the status of the bodies of `graph` that `classify` (the marker and
+    /// configuration classification) leaves unclassified. Destructors run by
+    /// drop glue count as calls. Recursive functions are safe unless something
+    /// else makes them otherwise.
+    pub fn build(
+        cx: &LateContext<'_>,
+        graph: &CallGraph,
+        classify: &dyn Fn(DefId) -> Option<Realtime>,
+    ) -> Self {
+        let tcx = cx.tcx;
+        // 1) What each body does by itself, and the destructors its drops run
+        let mut destructors = HashMap::new();
+        let own = graph
+            .bodies()
+            .filter(|body| {
+                matches!(
+                    tcx.def_kind(*body),
+                    DefKind::Fn | DefKind::AssocFn | DefKind::Closure
+                )
+            })
+            .filter(|body| classify(body.to_def_id()).is_none())
+            .map(|body| {
+                let drops = drops::find_drops(cx, body);
+                let freed = drops.iter().find(|site| {
+                    site.causes
+                        .iter()
+                        .any(|cause| matches!(cause, DropCause::Dealloc(_)))
+                });
+                let status = if let Some(alloc) = allocations::find_allocations(cx, body).first() {
+                    Inferred::Unsafe(format!("allocates: {} ({})", alloc.what, alloc.reason))
+                } else if let Some(site) = freed {
+                    Inferred::Unsafe(format!("drops a `{}`, which frees heap memory", site.ty))
+                } else if graph.calls_indirectly(body) {
+                    Inferred::Unknown("calls through a fn pointer or generic callable".to_string())
+                } else {
+                    Inferred::Safe
+                };
+                let dtors = drops
+                    .iter()
+                    .flat_map(|site| &site.causes)
+                    .filter_map(|cause| match cause {
+                        DropCause::Destructor(dtor) => Some(*dtor),
+                        DropCause::Dealloc(_) => None,
+                    })
+                    .collect::<Vec<_>>();
+                destructors.insert(body, dtors);
+                (body, status)
+            })
+            .collect::<HashMap<_, _>>();
+
+        // 2) Add what the callees do until nothing gets worse
+        let mut status = own.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (body, own) in &own {
+                let mut new = own.clone();
+                let callees = graph
+                    .calls_of(*body)
+                    .iter()
+                    .map(|site| site.callee)
+                    .chain(destructors[body].iter().copied());
+                for callee in callees {
+                    new = new.max(Self::callee_status(cx, &status, classify, callee));
+                }
+                if new.rank() > status[body].rank() {
+                    status.insert(*body, new);
+                    changed = true;
+                }
+            }
+        }
+        Self { status }
+    }
+
+    /// Status a call of `callee` gives its caller
+    fn callee_status(
+        cx: &LateContext<'_>,
+        status: &HashMap<LocalDefId, Inferred>,
+        classify: &dyn Fn(DefId) -> Option<Realtime>,
+        callee: DefId,
+    ) -> Inferred {
+        let tcx = cx.tcx;
+        let name = tcx.def_path_str(callee);
+        match classify(callee) {
+            Some(Realtime::Yes) => return Inferred::Safe,
+            Some(rt @ Realtime::No(_)) => return Inferred::Unsafe(rt.describe_call(&name)),
+            None => {}
+        }
+        // The implementation that runs is not known from the trait method
+        if tcx.trait_of_item(callee).is_some() {
+            return Inferred::Unknown(format!("calls trait method `{}`", name));
+        }
+        if let Some(inferred) = callee.as_local().and_then(|local| status.get(&local)) {
+            return match inferred {
+                Inferred::Safe => Inferred::Safe,
+                Inferred::Unknown(_) => Inferred::Unknown(format!("calls unknown `{}`", name)),
+                Inferred::Unsafe(_) => Inferred::Unsafe(format!("calls unsafe `{}`", name)),
+            };
+        }
+        if matches!(tcx.def_kind(callee), DefKind::Ctor(..)) {
+            return Inferred::Safe;
+        }
+        match knowledge::lookup(&knowledge::canonical_path(tcx, callee)) {
+            Some(api) if api.category == Category::Alloc => {
+                Inferred::Unsafe(format!("calls `{}` ({})", name, api.reason))
+            }
+            _ => Inferred::Unknown(format!("calls unannotated `{}`", name)),
+        }
+    }
+
+    pub fn get(&self, def_id: LocalDefId) -> Option<&Inferred> {
+        self.status.get(&def_id)
+    }
+
+    /// Write the inferred statuses as JSON lines, sorted by function, if a
+    /// dump is configured. `{crate}` in the path is replaced by the crate name.
+    pub fn dump(&self, cx: &LateContext<'_>, config: &Config) {
+        let Some(path) = report::setting(INFERRED_DUMP_ENV, config.inferred_dump.as_deref()) else {
+            return;
+        };
+        let tcx = cx.tcx;
+        let path = path.replace(
+            "{crate}",
+            tcx.crate_name(rustc_span::def_id::LOCAL_CRATE).as_str(),
+        );
+        let mut entries = self
+            .status
+            .iter()
+            .map(|(def_id, inferred)| {
+                let loc = tcx
+                    .sess
+                    .source_map()
+                    .lookup_char_pos(tcx.def_span(*def_id).lo());
+                Entry {
+                    function: tcx.def_path_str(def_id.to_def_id()),
+                    status: inferred.name(),
+                    reason: inferred.reason(),
+                    location: Location {
+                        file: loc.file.name.prefer_local().to_string(),
+                        line: loc.line,
+                        column: loc.col_display + 1,
+                    },
+                }
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.function.cmp(&b.function));
+        let written = File::create(&path).and_then(|mut file| {
+            for entry in &entries {
+                let line = serde_json::to_string(entry).expect("entry serializes");
+                writeln!(file, "{}", line)?;
+            }
+            Ok(())
+        });
+        match written {
+            Ok(()) => eprintln_ts!("[dylint] inferred classifications written to {}", path),
+            Err(err) => eprintln!(
+                "[dylint] cannot write inferred classifications {}: {}",
+                path, err
+            ),
+        }
+    }
+}
@@ -0,0 +1,320 @@
+// Built-in knowledge about std/core/alloc functions
+
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::{Body, SourceScope};
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{self, GenericArgsRef, Instance, TyCtxt, TypingEnv};
+use rustc_span::Span;
+
+/// Version of the built-in table, bumped whenever entries are added, removed
+/// or reclassified so that changes in reported violations can be traced back
+pub const KNOWLEDGE_BASE_VERSION: u32 = 3;
+
+/// Why a known function is a problem in realtime code
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    /// Allocates (or may allocate) heap memory
+    Alloc,
+    /// Sleeps, waits or otherwise gives up the CPU for an unbounded time
+    Blocking,
+    /// Acquires a blocking lock
+    Lock,
+    /// Performs I/O through a system call
+    Io,
+}
+
+/// An entry of the built-in table. `path` is either an exact canonical path
+/// or a `prefix::*` pattern.
+#[derive(Debug)]
+pub struct KnownApi {
+    pub path: &'static str,
+    pub category: Category,
+    pub reason: &'static str,
+}
+
+macro_rules! known {
+    ($($path:literal => $category:ident, $reason:literal;)*) => {
+        &[$(KnownApi { path: $path, category: Category::$category, reason: $reason },)*]
+    };
+}
+
+/// Known std APIs, keyed by canonical path (see [`canonical_path`])
+static KNOWN_APIS: &[KnownApi] = known! {
+    // Box / Rc / Arc creation
+    "std::boxed::box_new" => Alloc, "heap allocation";
+    "std::boxed::Box::new" => Alloc, "heap allocation";
+    "std::boxed::Box::pin" => Alloc, "heap allocation";
+    "std::rc::Rc::new" => Alloc, "heap allocation";
+    "std::sync::Arc::new" => Alloc, "heap allocation";
+    // Vec (`vec![]` expands to `from_elem` / `into_vec`)
+    "std::vec::from_elem" => Alloc, "heap allocation";
+    "std::slice::into_vec" => Alloc, "heap allocation";
+    "std::slice::to_vec" => Alloc, "heap allocation";
+    "std::vec::Vec::with_capacity" => Alloc, "heap allocation";
+    "std::vec::Vec::push" => Alloc, "may reallocate";
+    "std::vec::Vec::insert" => Alloc, "may reallocate";
+    "std::vec::Vec::append" => Alloc, "may reallocate";
+    "std::vec::Vec::extend_from_slice" => Alloc, "may reallocate";
+    "std::vec::Vec::resize" => Alloc, "may reallocate";
+    "std::vec::Vec::reserve" => Alloc, "may reallocate";
+    "std::vec::Vec::clone" => Alloc, "heap allocation";
+    "std::vec::Vec::from" => Alloc, "heap allocation";
+    "std::collections::VecDeque::push_back" => Alloc, "may reallocate";
+    "std::collections::VecDeque::push_front" => Alloc, "may reallocate";
+    "std::collections::HashMap::insert" => Alloc, "may reallocate";
+    "std::collections::BTreeMap::insert" => Alloc, "heap allocation";
+    // String (`format!` expands to `std::fmt::format`)
+    "std::fmt::format" => Alloc, "heap allocation";
+    "std::string::String::from" => Alloc, "heap allocation";
+    "std::string::String::with_capacity" => Alloc, "heap allocation";
+    "std::string::String::push" => Alloc, "may reallocate";
+    "std::string::String::push_str" => Alloc, "may reallocate";
+    "std::string::String::clone" => Alloc, "heap allocation";
+    "std::string::to_string" => Alloc, "heap allocation";
+    "std::str::to_owned" => Alloc, "heap allocation";
+    "std::str::to_string" => Alloc, "heap allocation";
+    // Raw allocator
+    "std::alloc::alloc" => Alloc, "heap allocation";
+    "std::alloc::alloc_zeroed" => Alloc, "heap allocation";
+    "std::alloc::realloc" => Alloc, "heap allocation";
+    "std::alloc::exchange_malloc" => Alloc, "heap allocation";
+    // Threads and waiting
+    "std::thread::sleep" => Blocking, "sleeps";
+    "std::thread::sleep_ms" => Blocking, "sleeps";
+    "std::thread::park" => Blocking, "parks the thread";
+    "std::thread::park_timeout" => Blocking, "parks the thread";
+    "std::thread::spawn" => Blocking, "spawns a thread";
+    "std::thread::Builder::spawn" => Blocking, "spawns a thread";
+    "std::sync::mpsc::Receiver::recv" => Blocking, "blocks on a channel";
+    "std::sync::mpsc::Receiver::recv_timeout" => Blocking, "blocks on a channel";
+    "std::sync::mpsc::SyncSender::send" => Blocking, "blocks on a channel";
+    "std::sync::Once::call_once" => Blocking, "may block on initialization";
+    "std::sync::OnceLock::get_or_init" => Blocking, "may block on initialization";
+    "std::sync::LazyLock::force" => Blocking, "may block on initialization";
+    "std::env::var" => Blocking, "takes the environment lock";
+    "std::env::set_var" => Blocking, "takes the environment lock";
+    // Locks
+    "std::sync::Mutex::lock" => Lock, "blocking lock";
+    "std::sync::RwLock::read" => Lock, "blocking lock";
+    "std::sync::RwLock::write" => Lock, "blocking lock";
+    "std::sync::Condvar::wait" => Lock, "blocking lock";
+    "std::sync::Condvar::wait_while" => Lock, "blocking lock";
+    "std::sync::Condvar::wait_timeout" => Lock, "blocking lock";
+    "std::sync::Condvar::wait_timeout_while" => Lock, "blocking lock";
+    "std::sync::Barrier::wait" => Lock, "blocking lock";
+    "std::thread::JoinHandle::join" => Lock, "waits for a thread";
+    "std::thread::ScopedJoinHandle::join" => Lock, "waits for a thread";
+    // parking_lot (its lock types are `lock_api` types)
+    "lock_api::Mutex::lock" => Lock, "blocking lock";
+    "lock_api::ReentrantMutex::lock" => Lock, "blocking lock";
+    "lock_api::RwLock::read" => Lock, "blocking lock";
+    "lock_api::RwLock::read_recursive" => Lock, "blocking lock";
+    "lock_api::RwLock::write" => Lock, "blocking lock";
+    "lock_api::RwLock::upgradable_read" => Lock, "blocking lock";
+    "lock_api::RwLockUpgradableReadGuard::upgrade" => Lock, "blocking lock";
+    "parking_lot::Condvar::wait" => Lock, "blocking lock";
+    "parking_lot::Condvar::wait_for" => Lock, "blocking lock";
+    "parking_lot::Condvar::wait_until" => Lock, "blocking lock";
+    "parking_lot::Condvar::wait_while" => Lock, "blocking lock";
+    "parking_lot::Once::call_once" => Blocking, "may block on initialization";
+    // I/O (`println!` expands to `std::io::_print`)
+    "std::io::_print" => Io, "stdio";
+    "std::io::_eprint" => Io, "stdio";
+    "std::io::stdin" => Io, "stdio";
+    "std::io::stdout" => Io, "stdio";
+    "std::io::stderr" => Io, "stdio";
+    "std::io::Stdin::*" => Io, "stdio";
+    "std::io::Stdout::*" => Io, "stdio";
+    "std::io::Stderr::*" => Io, "stdio";
+    "std::io::StdinLock::*" => Io, "stdio";
+    "std::io::StdoutLock::*" => Io, "stdio";
+    "std::io::StderrLock::*" => Io, "stdio";
+    "std::fs::*" => Io, "file I/O";
+    "std::net::*" => Io, "network I/O";
+    "std::process::*" => Io, "process management";
+    // libc system calls
+    "libc::read" => Io, "system call";
+    "libc::write" => Io, "system call";
+    "libc::pread" => Io, "system call";
+    "libc::pwrite" => Io, "system call";
+    "libc::readv" => Io, "system call";
+    "libc::writev" => Io, "system call";
+    "libc::open" => Io, "system call";
+    "libc::openat" => Io, "system call";
+    "libc::close" => Io, "system call";
+    "libc::fsync" => Io, "system call";
+    "libc::ioctl" => Io, "system call";
+    "libc::recv" => Io, "system call";
+    "libc::recvfrom" => Io, "system call";
+    "libc::recvmsg" => Io, "system call";
+    "libc::send" => Io, "system call";
+    "libc::sendto" => Io, "system call";
+    "libc::sendmsg" => Io, "system call";
+    "libc::accept" => Io, "system call";
+    "libc::connect" => Io, "system call";
+    "libc::poll" => Io, "system call";
+    "libc::select" => Io, "system call";
+    "libc::epoll_wait" => Io, "system call";
+    "libc::printf" => Io, "stdio";
+    "libc::puts" => Io, "stdio";
+};
+
+/// Look up a known API by canonical path
+pub fn lookup(path: &str) -> Option<&'static KnownApi> {
+    KNOWN_APIS.iter().find(|api| path_matches(api.path, path))
+}
+
+/// Non-blocking replacement to suggest for a known lock API
+pub fn lock_alternative(path: &str) -> &'static str {
+    match path.rsplit("::").next() {
+        Some("lock") => "use `try_lock` and skip the work when the lock is contended, or a lock-free structure",
+        Some("read" | "read_recursive") => "use `try_read` and skip the work when the lock is contended, or publish the data lock-free (e.g. double buffering)",
+        Some("write" | "upgradable_read" | "upgrade") => "use `try_write` and skip the work when the lock is contended, or publish the data lock-free (e.g. double buffering)",
+        Some("join") => "hand results back through a lock-free queue instead of waiting for the thread",
+        _ => "signal the realtime thread without blocking, e.g. through a lock-free queue or an atomic flag",
+    }
+}
+
+/// Whether `path` is matched by `pattern` (exact, or `prefix::*`)
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix("*") {
+        Some(prefix) => path.starts_with(prefix),
+        None => pattern == path,
+    }
+}
+
+/// Resolve a (possibly trait) function to the item that will actually run,
+/// e.g. `From::from` with `Self = String` to `<String as From<&str>>::from`
+pub fn resolve_callee<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    typing_env: TypingEnv<'tcx>,
+    def_id: DefId,
+    args: GenericArgsRef<'tcx>,
+) -> DefId {
+    if !matches!(tcx.def_kind(def_id), DefKind::Fn | DefKind::AssocFn) {
+        return def_id;
+    }
+    match Instance::try_resolve(tcx, typing_env, def_id, args) {
+        Ok(Some(instance)) if matches!(instance.def, ty::InstanceKind::Item(_)) => {
+            instance.def_id()
+        }
+        _ => def_id,
+    }
+}
+
+/// The function called by `body` whose inlined code `scope` belongs to, with
+/// the span of the call.
+///
+/// MIR inlining copies callee bodies into their callers, but what a realtime
+/// body does is decided at the call: a local callee is checked at its own
+/// definition or through the call graph, and a std callee is classified as a
+/// whole by the built-in table. Checks on MIR therefore look at the call of an
+/// inlined function only, never at the code copied from it.
+pub fn inlined_call<'tcx>(body: &Body<'tcx>, scope: SourceScope) -> Option<(Instance<'tcx>, Span)> {
+    let scopes = &body.source_scopes;
+    let mut inlined = match scopes[scope].inlined {
+        Some(_) => scope,
+        None => scopes[scope].inlined_parent_scope?,
+    };
+    // Functions inlined into inlined functions hang below the outermost one
+    while let Some(outer) = scopes[inlined].inlined_parent_scope {
+        inlined = outer;
+    }
+    scopes[inlined].inlined
+}
+
+/// Path of a function with generic arguments dropped and impl blocks flattened
+/// into their self type (or their module for non-ADT self types), with
+/// `core::` / `alloc::` normalized to `std::`. For instance
+/// `<Vec<T, A>>::push` gives `std::vec::Vec::push` and `<[T]>::into_vec`
+/// gives `std::slice::into_vec`.
+pub fn canonical_path(tcx: TyCtxt<'_>, def_id: DefId) -> String {
+    let path = with_no_trimmed_paths!(match tcx.impl_of_method(def_id) {
+        Some(impl_id) => {
+            let base = match tcx.type_of(impl_id).instantiate_identity().kind() {
+                ty::Adt(adt, _) => tcx.def_path_str(adt.did()),
+                _ => tcx.def_path_str(tcx.parent(impl_id)),
+            };
+            format!("{}::{}", base, tcx.item_name(def_id))
+        }
+        None => tcx.def_path_str(def_id),
+    });
+    normalize_path(&path)
+}
+
+/// Path of a trait method run from the trait's default body on a concrete
+/// type, under that type: `Write::write_fmt` with `Self = StdoutLock` gives
+/// `std::io::StdoutLock::write_fmt`
+pub fn inherited_path<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    def_id: DefId,
+    args: GenericArgsRef<'tcx>,
+) -> Option<String> {
+    tcx.trait_of_item(def_id)?;
+    let ty::Adt(adt, _) = args.type_at(0).peel_refs().kind() else {
+        return None;
+    };
+    let base = with_no_trimmed_paths!(tcx.def_path_str(adt.did()));
+    Some(normalize_path(&format!(
+        "{}::{}",
+        base,
+        tcx.item_name(def_id)
+    )))
+}
+
+fn normalize_path(path: &str) -> String {
+    // Drop generic arguments: `Vec::<T, A>::push` -> `Vec::push`
+    let mut out = String::with_capacity(path.len());
+    let mut depth = 0usize;
+    for c in path.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => out.push(c),
+            _ => {}
+        }
+    }
+    let out = out.replace("::::", "::");
+    for krate in ["core::", "alloc::"] {
+        if let Some(rest) = out.strip_prefix(krate) {
+            return format!("std::{}", rest);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lock_alternative, normalize_path, path_matches};
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            normalize_path("std::vec::Vec::<T, A>::push"),
+            "std::vec::Vec::push"
+        );
+        assert_eq!(
+            normalize_path("alloc::boxed::box_new"),
+            "std::boxed::box_new"
+        );
+        assert_eq!(
+            normalize_path("core::iter::Iterator::for_each"),
+            "std::iter::Iterator::for_each"
+        );
+    }
+
+    #[test]
+    fn lock_alternatives() {
+        assert!(lock_alternative("std::sync::Mutex::lock").contains("try_lock"));
+        assert!(lock_alternative("lock_api::RwLock::write").contains("try_write"));
+        assert!(lock_alternative("std::sync::Condvar::wait").contains("without blocking"));
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(path_matches("std::fs::*", "std::fs::File::open"));
+        assert!(!path_matches("std::fs::*", "std::fmt::format"));
+        assert!(path_matches("std::fmt::format", "std::fmt::format"));
+    }
+}
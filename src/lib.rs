@@ -2,14 +2,28 @@
 #![feature(rustc_private)]
 #![warn(unused_extern_crates)]
 
+extern crate rustc_errors;
 extern crate rustc_hir;
+extern crate rustc_lint;
+extern crate rustc_middle;
+extern crate rustc_session;
 extern crate rustc_span;
 
+use callgraph::{CallGraph, CallSite};
+use config::{Class, ClosurePolicy, Config};
+use fnptr::FnPtrFlow;
+use inference::{Inference, Inferred};
+use knowledge::Category;
+use report::{CallKind, Location, Report, Step, Violation};
+
+use rustc_errors::Diag;
 use rustc_hir as hir;
-use rustc_hir::def::{DefKind, Res};
-use rustc_lint::{LateContext, LateLintPass, LintContext};
+use rustc_hir::def::DefKind;
+use rustc_lint::{LateContext, LateLintPass, Level, Lint, LintContext};
+use rustc_middle::ty;
 use rustc_span::{Span, Symbol};
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 use std::time::Instant;
 
@@ -21,16 +35,66 @@ fn timestamp_prefix() -> String {
     format!("[{:>6}.{:03}s]", elapsed.as_secs(), elapsed.subsec_millis())
 }
 
+/// Environment variable enabling the debug output of the lints on stderr
+const DEBUG_ENV: &str = "RT_LINT_DEBUG";
+
+fn debug_enabled() -> bool {
+    static DEBUG: OnceLock<bool> = OnceLock::new();
+    *DEBUG.get_or_init(|| std::env::var_os(DEBUG_ENV).is_some())
+}
+
 macro_rules! eprintln_ts {
     ($($arg:tt)*) => {{
-        eprintln!("{} {}", crate::timestamp_prefix(), format!($($arg)*));
+        if crate::debug_enabled() {
+            eprintln!("{} {}", crate::timestamp_prefix(), format!($($arg)*));
+        }
     }};
 }
 
-// Use dylint's impl_late_lint! macro to allow a custom struct
-dylint_linting::impl_late_lint! {
+mod allocations;
+mod callgraph;
+mod config;
+mod dispatch;
+mod drops;
+mod fnptr;
+mod implicit;
+mod inference;
+mod knowledge;
+mod mono;
+mod panics;
+mod report;
+
+// Several lints share one pass (and its realtime-context tracking), so the
+// library registers them itself instead of using impl_late_lint!
+dylint_linting::dylint_library!();
+
+#[no_mangle]
+pub fn register_lints(sess: &rustc_session::Session, lint_store: &mut rustc_lint::LintStore) {
+    dylint_linting::init_config(sess);
+    lint_store.register_lints(&RealtimeCallsNonrealtime::lint_vec());
+    lint_store.register_late_pass(|_| Box::new(RealtimeCallsNonrealtime::new()));
+}
+
+rustc_session::declare_lint! {
     /// ### What it does
-    /// Checks whether a realtime function (marked rt:realtime in main.rs) calls a non-realtime function
+    /// Checks whether a realtime function (marked `#[realtime]`, in any module or
+    /// crate) calls a non-realtime function
+    ///
+    /// Unmarked std functions are classified by a built-in, versioned table of
+    /// blocking APIs (e.g. `std::thread::sleep`, `std::sync::mpsc::Receiver::recv`);
+    /// its locks are reported by `REALTIME_LOCKS` and its I/O by `REALTIME_IO`.
+    ///
+    /// Method calls on a trait object (`s.area()` with `s: &dyn Shape`) are
+    /// checked against every implementation of the method in the crate graph.
+    /// Closures passed as arguments to a realtime function are checked as
+    /// realtime code, wherever they are defined. Calls through fn pointers stored
+    /// in struct fields, arrays, statics and `Option<fn()>`s are checked against
+    /// every function stored in such a place anywhere in the crate; calls through
+    /// local variables only against what the variable may hold at the call,
+    /// following reassignments, shadowing and branches. Calls the compiler
+    /// inserts (overloaded operators, `Deref` coercions, indexing, the `From`
+    /// conversion of `?`, `IntoIterator` in `for` loops) are taken from the MIR
+    /// and checked against the implementation they run.
     ///
     /// ### Why is this bad?
     /// Realtime functions should only call other realtime functions to preserve realtime performance characteristics
@@ -41,59 +105,419 @@ dylint_linting::impl_late_lint! {
     /// ### Example
     ///
     /// ```rust
-    /// /// rt:realtime
+    /// #[doc = "rt:realtime"]
     /// fn realtime_fn() {
     ///     non_realtime_fn(); // This will trigger a warning
     /// }
     ///
-    /// fn non_realtime_fn() {
-    ///     // Function not marked as realtime
-    /// }
+    /// #[doc = "rt:non_realtime"]
+    /// fn non_realtime_fn() {}
     /// ```
     pub REALTIME_CALLS_NONREALTIME,
     Warn,
-    "a realtime function (in main.rs) calls a non-realtime function",
-    RealtimeCallsNonrealtime::new()  // Pass our custom struct instance
+    "a realtime function calls a non-realtime function"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks whether a realtime function allocates heap memory, through std APIs
+    /// such as `vec!`, `format!`, `Box::new`, `String::from` or `Vec::push`, or
+    /// through box creation and raw allocator calls in its MIR. Values of heap
+    /// owning types (`Box`, `Vec`, `String`, the std collections) dropped in the
+    /// function are reported too, as their drop glue frees memory. Unannotated
+    /// functions it calls that allocate or free memory, directly or through
+    /// other unannotated functions, are reported at the call. Closures are
+    /// checked when they are realtime themselves: by their marker, when passed
+    /// to a realtime function, or per the `closure_policy` setting.
+    ///
+    /// ### Why is this bad?
+    /// The global allocator may take locks or ask the OS for memory, so its
+    /// latency is unbounded
+    ///
+    /// ### Known problems
+    /// Only the std APIs in the built-in table are recognised; allocations behind
+    /// other external functions are not seen.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// fn process(buffer: &mut [f32]) {
+    ///     let scratch = vec![0.0f32; buffer.len()]; // This will trigger a warning
+    /// }
+    /// ```
+    pub REALTIME_ALLOCATES,
+    Warn,
+    "a realtime function allocates or frees heap memory"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks that every implementation of a trait method marked realtime honours
+    /// that contract. Unmarked implementations inherit it and are checked like
+    /// realtime functions; implementations marked non-realtime are reported.
+    /// Every violation inside such an implementation, locks and I/O included,
+    /// is reported by this lint.
+    ///
+    /// ### Why is this bad?
+    /// Realtime code calling the trait method relies on the contract, whichever
+    /// implementation ends up running
+    ///
+    /// ### Known problems
+    /// None.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// trait AudioProcessor {
+    ///     #[realtime]
+    ///     fn process_audio(&self, buffer: &mut [f32]);
+    /// }
+    ///
+    /// impl AudioProcessor for SimpleProcessor {
+    ///     fn process_audio(&self, buffer: &mut [f32]) {
+    ///         println!("Processing audio buffer"); // This will trigger a warning
+    ///     }
+    /// }
+    /// ```
+    pub REALTIME_IMPL_VIOLATES_CONTRACT,
+    Warn,
+    "an implementation of a realtime trait method is not realtime"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// With `strict = true` in dylint.toml, checks that a realtime function only
+    /// calls functions somebody vouched for: marked `#[realtime]` or
+    /// `#[non_realtime]`, classified by an `external` rule or the built-in table,
+    /// or allowed by a `strict_allow` pattern. Calls on trait objects and through
+    /// fn pointers are checked against every function they may run; calls
+    /// through fn pointers that may run any function (parameters, results of
+    /// calls) are reported.
+    ///
+    /// ### Why is this bad?
+    /// An unannotated function is only checked through what it calls in this
+    /// crate graph, so it may still block in ways the lint cannot see
+    ///
+    /// ### Known problems
+    /// Without a `strict_allow` entry such as `"std::*"`, every unlisted std
+    /// function is reported.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// fn helper() {}
+    ///
+    /// #[realtime]
+    /// fn process() {
+    ///     helper(); // This will trigger an error in strict mode
+    /// }
+    /// ```
+    pub REALTIME_CALLS_UNANNOTATED,
+    Deny,
+    "a realtime function calls a function that is neither marked nor classified"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks the body of every function marked `#[realtime]` against its
+    /// marker, wherever it is defined: the non-realtime work found in the body
+    /// (non-realtime calls, heap operations, blocking locks and I/O) is counted
+    /// at the function definition, whether or not the lints reporting each
+    /// violation are enabled. Unannotated calls in strict mode and panic paths
+    /// are not counted.
+    ///
+    /// ### Why is this bad?
+    /// Callers in other crates trust the marker without seeing the body, so a
+    /// function that does not live up to it is a problem of its own
+    ///
+    /// ### Known problems
+    /// Allowing the lint that reports a violation does not stop it from being
+    /// counted here: silencing a violation in a marked function takes allowing
+    /// both that lint and this one.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// pub fn do_rt() { // This will trigger a warning
+    ///     println!("Execute realtime function: do_rt");
+    /// }
+    /// ```
+    pub REALTIME_FN_NOT_REALTIME,
+    Warn,
+    "a function marked realtime does non-realtime work"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks whether a realtime function acquires a blocking lock or waits on
+    /// another thread: `std::sync::Mutex::lock`, `RwLock::read` / `write`,
+    /// `Condvar::wait`, `Barrier::wait`, `JoinHandle::join` and their
+    /// `parking_lot` equivalents, directly or through unannotated functions.
+    /// These calls are reported by this lint instead of
+    /// `REALTIME_CALLS_NONREALTIME`, except in implementations of realtime
+    /// trait methods, where `REALTIME_IMPL_VIOLATES_CONTRACT` reports them.
+    ///
+    /// ### Why is this bad?
+    /// The thread holding the lock may be preempted by lower-priority work, so
+    /// the realtime thread can wait for an unbounded time (priority inversion)
+    ///
+    /// ### Known problems
+    /// Only the locks of the built-in table are recognised; locks classified in
+    /// dylint.toml are reported by `REALTIME_CALLS_NONREALTIME`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// fn process(state: &Mutex<State>) {
+    ///     let state = state.lock().unwrap(); // This will trigger a warning
+    /// }
+    /// ```
+    ///
+    /// Use instead:
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// fn process(state: &Mutex<State>) {
+    ///     if let Ok(state) = state.try_lock() {
+    ///         // ...
+    ///     }
+    /// }
+    /// ```
+    pub REALTIME_LOCKS,
+    Warn,
+    "a realtime function acquires a blocking lock"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks whether a realtime function performs I/O or system calls: file,
+    /// socket and process operations (`std::fs`, `std::net`, `std::process`),
+    /// stdio (`println!`, `std::io::Write` on stdout / stderr) and `libc`
+    /// calls such as `read` and `write`, directly or through unannotated
+    /// functions. These calls are reported by this lint instead of
+    /// `REALTIME_CALLS_NONREALTIME`, except in implementations of realtime
+    /// trait methods, where `REALTIME_IMPL_VIOLATES_CONTRACT` reports them.
+    ///
+    /// ### Why is this bad?
+    /// System calls enter the kernel and may block on devices, the network or
+    /// locks held by other processes
+    ///
+    /// ### Known problems
+    /// Only the APIs of the built-in table are recognised; I/O classified in
+    /// dylint.toml is reported by `REALTIME_CALLS_NONREALTIME`.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// pub fn do_rt() {
+    ///     println!("Execute realtime function: do_rt"); // This will trigger a warning
+    /// }
+    /// ```
+    pub REALTIME_IO,
+    Warn,
+    "a realtime function performs I/O or system calls"
+}
+
+rustc_session::declare_lint! {
+    /// ### What it does
+    /// Checks realtime bodies for panic paths: explicit panics (`panic!`,
+    /// `unreachable!`, `assert!`, ...), `unwrap` / `expect`, and the bounds,
+    /// overflow and division checks the compiler inserts. Each function gets one
+    /// diagnostic counting its panic paths, with a note per site.
+    ///
+    /// This lint is allowed by default; enable it with
+    /// `#![warn(realtime_panics)]` or `#![deny(realtime_panics)]`.
+    ///
+    /// ### Why is this bad?
+    /// A panic formats its message, may allocate and unwinds the stack, none of
+    /// which has a bounded latency
+    ///
+    /// ### Known problems
+    /// Overflow checks only exist when they are enabled (`debug-assertions` or
+    /// `overflow-checks`); panics in called functions are not reported.
+    ///
+    /// ### Example
+    ///
+    /// ```rust,ignore
+    /// #[realtime]
+    /// fn process(buffer: &mut [f32], gain: Option<f32>) { // This will trigger a warning
+    ///     buffer[0] *= gain.unwrap();
+    /// }
+    /// ```
+    pub REALTIME_PANICS,
+    Allow,
+    "a realtime function may panic"
+}
+
+rustc_session::impl_lint_pass!(RealtimeCallsNonrealtime => [
+    REALTIME_CALLS_NONREALTIME,
+    REALTIME_ALLOCATES,
+    REALTIME_IMPL_VIOLATES_CONTRACT,
+    REALTIME_CALLS_UNANNOTATED,
+    REALTIME_FN_NOT_REALTIME,
+    REALTIME_LOCKS,
+    REALTIME_IO,
+    REALTIME_PANICS
+]);
+
+/// Realtime classification of a function, from its marker or the built-in table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Realtime {
+    Yes,
+    /// Non-realtime, with the reason when one is known
+    No(Option<String>),
+}
+
+impl Realtime {
+    /// "calls `callee`, which is non-realtime because: <reason>" for diagnostics
+    pub fn describe_call(&self, callee: &str) -> String {
+        match self {
+            Realtime::No(Some(reason)) => format!(
+                "calls `{}`, which is non-realtime because: {}",
+                callee, reason
+            ),
+            _ => format!("calls non-realtime `{}`", callee),
+        }
+    }
+
+    /// Reason of a non-realtime classification, if known
+    fn reason(self) -> Option<String> {
+        match self {
+            Realtime::No(reason) => reason,
+            Realtime::Yes => None,
+        }
+    }
+}
+
+/// Why a body is checked as realtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Origin {
+    /// Marked realtime
+    Marked,
+    /// Implements this trait method, which is marked realtime
+    Contract(rustc_hir::def_id::DefId),
+    /// A closure passed as an argument to this realtime function
+    PassedTo(rustc_hir::def_id::DefId),
+}
+
+/// A realtime body being checked
+#[derive(Clone, Copy, Debug)]
+struct RealtimeContext {
+    def_id: rustc_span::def_id::LocalDefId,
+    origin: Origin,
+}
+
+/// A function or closure body being checked, with its realtime context if any.
+/// Closures defined in a realtime function may carry the enclosing context.
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+    body: rustc_span::def_id::LocalDefId,
+    realtime: Option<RealtimeContext>,
+}
+
+/// A heap allocation or deallocation in the body of an unannotated function
+struct HeapOp {
+    span: Span,
+    /// "allocates" or "frees heap memory"
+    action: &'static str,
+    what: String,
+    /// Name of the allocating function or of the type owning the memory freed
+    callee: String,
+    reason: &'static str,
 }
 
 #[derive(Default)]
 pub struct RealtimeCallsNonrealtime {
-    // Whether we are inside the 'realtime function body' in main.rs (used as a stack)
-    in_realtime_main_fn: Vec<hir::HirId>,
-    // Record realtime property for closures bound by let: variable name -> is realtime
-    closure_var_realtime: HashMap<String, bool>,
-    // Record realtime property for function-pointer variables bound by let: variable name -> is realtime
-    fnptr_var_realtime: HashMap<String, bool>,
+    // Function and closure bodies being checked, innermost last: nested fns and
+    // closures are checked in the middle of the enclosing body
+    body_stack: Vec<Frame>,
+    // Closures bound by let with an rt_call_info marker -> is realtime
+    closure_marks: HashMap<rustc_span::def_id::LocalDefId, bool>,
+    // Closures passed as arguments of realtime functions -> that function
+    realtime_closures: HashMap<rustc_span::def_id::LocalDefId, rustc_hir::def_id::DefId>,
+    // Calls of every local body, used to look through unannotated callees
+    call_graph: CallGraph,
+    // Inferred realtime safety of unannotated local functions
+    inference: Inference,
+    // Functions stored in fn-pointer fields, arrays and statics
+    fnptr_flow: FnPtrFlow,
+    // Functions each call through a fn-pointer variable of a realtime body may
+    // run, by call expression
+    local_fnptr_targets: HashMap<hir::HirId, Vec<rustc_hir::def_id::DefId>>,
+    // Classification of the external functions looked up so far
+    external_classes: RefCell<HashMap<rustc_hir::def_id::DefId, Option<Realtime>>>,
+    // Non-realtime work found in each realtime body, by site, counted at the
+    // definitions of marked functions
+    violations: RefCell<HashMap<rustc_span::def_id::LocalDefId, Vec<(Span, &'static str)>>>,
+    // Settings from dylint.toml
+    config: Config,
+    // Machine-readable reports of the violations, when enabled
+    report: Report,
 }
 
 impl RealtimeCallsNonrealtime {
     pub fn new() -> Self {
+        let config = Config::load();
         Self {
-            in_realtime_main_fn: Vec::new(),
-            closure_var_realtime: HashMap::new(),
-            fnptr_var_realtime: HashMap::new(),
+            body_stack: Vec::new(),
+            closure_marks: HashMap::new(),
+            realtime_closures: HashMap::new(),
+            call_graph: CallGraph::default(),
+            inference: Inference::default(),
+            fnptr_flow: FnPtrFlow::default(),
+            local_fnptr_targets: HashMap::new(),
+            external_classes: RefCell::default(),
+            violations: RefCell::default(),
+            report: Report::open(&config),
+            config,
         }
     }
 
     fn in_realtime(&self) -> bool {
-        !self.in_realtime_main_fn.is_empty()
+        self.realtime_context().is_some()
+    }
+
+    /// Realtime context of the innermost body being checked
+    fn realtime_context(&self) -> Option<RealtimeContext> {
+        self.body_stack.last().and_then(|frame| frame.realtime)
     }
 
-    /// Read the doc marker on a function:
-    ///  - rt:realtime            => Some(true)
-    ///  - rt:nonrealtime:...     => Some(false)
-    ///  - not marked             => None
-    fn doc_marker_is_realtime(_cx: &LateContext<'_>, attrs: &[hir::Attribute]) -> Option<bool> {
+    /// Read the marker on a function, either the doc marker or (with the
+    /// `tool-attrs` feature of rt_attrs) the `rt::` tool attribute:
+    ///  - rt:realtime                  | #[rt::realtime]                => Some(Yes)
+    ///  - rt:non_realtime[:reason]     | #[rt::non_realtime[ = reason]] => Some(No(reason))
+    ///  - not marked                   => None
+    ///
+    /// Doc markers must be the whole doc string, so documentation that merely
+    /// mentions a marker does not mark the function.
+    fn doc_marker_is_realtime<'a>(
+        _cx: &LateContext<'_>,
+        attrs: impl IntoIterator<Item = &'a hir::Attribute>,
+    ) -> Option<Realtime> {
+        let rt = Symbol::intern("rt");
         for attr in attrs {
+            // Check tool attributes
+            if attr.path_matches(&[rt, Symbol::intern("realtime")]) {
+                return Some(Realtime::Yes);
+            }
+            if attr.path_matches(&[rt, Symbol::intern("non_realtime")]) {
+                return Some(Realtime::No(attr.value_str().map(|s| s.to_string())));
+            }
             // Check doc comment attributes
             if attr.has_name(Symbol::intern("doc")) {
                 if let Some(content) = attr.value_str() {
-                    let doc_str = content.as_str();
-                    if doc_str.contains("rt:realtime") {
-                        return Some(true);
-                    }
-                    if doc_str.contains("rt:non_realtime") {
-                        return Some(false);
+                    match content.as_str() {
+                        "rt:realtime" => return Some(Realtime::Yes),
+                        "rt:non_realtime" => return Some(Realtime::No(None)),
+                        doc_str => {
+                            if let Some(reason) = doc_str.strip_prefix("rt:non_realtime:") {
+                                return Some(Realtime::No(Some(reason.to_string())));
+                            }
+                        }
                     }
                 }
             }
@@ -123,148 +547,1480 @@ impl RealtimeCallsNonrealtime {
     }
 
     // Realtime determination for ordinary functions and traits
-    fn callee_is_realtime(cx: &LateContext<'_>, def_id: rustc_hir::def_id::DefId) -> Option<bool> {
+    fn callee_is_realtime(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<Realtime> {
         // 首先检查是否为本地定义
         if !def_id.is_local() {
-            // External crate: treat as unmarked (could be relaxed)
-            eprintln_ts!(
-                "External DefId: {:?}, Path: {}",
+            if let Some(is_rt) = self.external_classes.borrow().get(&def_id) {
+                return is_rt.clone();
+            }
+            let is_rt = self.external_is_realtime(cx, def_id);
+            self.external_classes
+                .borrow_mut()
+                .insert(def_id, is_rt.clone());
+            return is_rt;
+        }
+
+        // Unmarked functions are realtime if inferred safe; unsafe ones are
+        // reported with their path by the transitive analysis.
+        self.vouched_class(cx, def_id).or_else(|| {
+            matches!(
+                self.inference.get(def_id.expect_local()),
+                Some(Inferred::Safe)
+            )
+            .then_some(Realtime::Yes)
+        })
+    }
+
+    /// Classification of `def_id` somebody vouched for: `callee_is_realtime`
+    /// without inference, which strict mode does not take as a vouch
+    fn vouched_class(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<Realtime> {
+        match def_id.as_local() {
+            // Unmarked impls of realtime trait methods are held to the trait's
+            // contract (REALTIME_IMPL_VIOLATES_CONTRACT), so callers can rely on it
+            Some(local) => {
+                let attrs = cx.tcx.hir_attrs(cx.tcx.local_def_id_to_hir_id(local));
+                Self::doc_marker_is_realtime(cx, attrs)
+                    .or_else(|| self.realtime_contract(cx, def_id).map(|_| Realtime::Yes))
+            }
+            None => self.callee_is_realtime(cx, def_id),
+        }
+    }
+
+    /// The trait method implemented by `def_id`, if that method is marked realtime
+    fn realtime_contract(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<rustc_hir::def_id::DefId> {
+        // Trait items name themselves as their trait item
+        let trait_item = cx
+            .tcx
+            .opt_associated_item(def_id)?
+            .trait_item_def_id
+            .filter(|trait_item| *trait_item != def_id)?;
+        matches!(self.callee_is_realtime(cx, trait_item), Some(Realtime::Yes)).then_some(trait_item)
+    }
+
+    /// Realtime context of a closure body: from its rt_call_info marker, from
+    /// the realtime function it is passed to, or else from the enclosing body
+    /// under the `inherit` closure policy
+    fn closure_context(&self, def_id: rustc_span::def_id::LocalDefId) -> Option<RealtimeContext> {
+        if let Some(is_rt) = self.closure_marks.get(&def_id) {
+            return is_rt.then_some(RealtimeContext {
                 def_id,
-                cx.tcx.def_path_str(def_id)
+                origin: Origin::Marked,
+            });
+        }
+        if let Some(callee) = self.realtime_closures.get(&def_id) {
+            return Some(RealtimeContext {
+                def_id,
+                origin: Origin::PassedTo(*callee),
+            });
+        }
+        match self.config.closure_policy {
+            ClosurePolicy::Inherit => self.realtime_context(),
+            ClosurePolicy::Explicit => None,
+        }
+    }
+
+    /// Lint for non-realtime calls in the current context: functions that are
+    /// realtime through a trait contract report them as contract violations
+    fn call_lint(&self) -> &'static Lint {
+        match self.realtime_context() {
+            Some(RealtimeContext {
+                origin: Origin::Contract(_),
+                ..
+            }) => REALTIME_IMPL_VIOLATES_CONTRACT,
+            _ => REALTIME_CALLS_NONREALTIME,
+        }
+    }
+
+    /// Category of the built-in table `def_id` is classified by, if neither its
+    /// markers nor dylint.toml classify it
+    fn builtin_category(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<Category> {
+        if def_id.is_local()
+            || Self::doc_marker_is_realtime(cx, cx.tcx.get_all_attrs(def_id)).is_some()
+        {
+            return None;
+        }
+        let path = knowledge::canonical_path(cx.tcx, def_id);
+        if self.config.external_rule(&path).is_some() {
+            return None;
+        }
+        knowledge::lookup(&path).map(|api| api.category)
+    }
+
+    /// Lint for reaching the non-realtime `callee`: blocking locks and I/O have
+    /// lints of their own, other calls get the lint of the context. Inside an
+    /// implementation of a realtime trait method everything is a contract
+    /// violation
+    fn lint_for(&self, cx: &LateContext<'_>, callee: rustc_hir::def_id::DefId) -> &'static Lint {
+        self.category_lint(self.builtin_category(cx, callee))
+    }
+
+    fn category_lint(&self, category: Option<Category>) -> &'static Lint {
+        let in_contract = matches!(
+            self.realtime_context(),
+            Some(RealtimeContext {
+                origin: Origin::Contract(_),
+                ..
+            })
+        );
+        match category {
+            Some(Category::Lock) if !in_contract => REALTIME_LOCKS,
+            Some(Category::Io) if !in_contract => REALTIME_IO,
+            _ => self.call_lint(),
+        }
+    }
+
+    /// Suggest a replacement when `callee` is a known lock or I/O API
+    fn help_alternative(
+        &self,
+        cx: &LateContext<'_>,
+        diag: &mut Diag<'_, ()>,
+        callee: rustc_hir::def_id::DefId,
+    ) {
+        let category = self.builtin_category(cx, callee);
+        Self::help_category(diag, category, &knowledge::canonical_path(cx.tcx, callee));
+    }
+
+    fn help_category(diag: &mut Diag<'_, ()>, category: Option<Category>, path: &str) {
+        match category {
+            Some(Category::Lock) => {
+                diag.help(knowledge::lock_alternative(path));
+            }
+            Some(Category::Io) => {
+                diag.help(
+                    "hand the data to a non-realtime thread (e.g. through a lock-free queue) and do the I/O there",
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// The implementation a trait method call on a concrete type runs, when
+    /// the trait method itself is unclassified: `out.write_all(..)` with
+    /// `out: Stdout` is classified as `<Stdout as Write>::write_all`
+    fn concrete_callee(
+        &self,
+        cx: &LateContext<'_>,
+        expr: &hir::Expr<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> rustc_hir::def_id::DefId {
+        if cx.tcx.trait_of_item(def_id).is_none() || self.callee_is_realtime(cx, def_id).is_some() {
+            return def_id;
+        }
+        knowledge::resolve_callee(
+            cx.tcx,
+            cx.typing_env(),
+            def_id,
+            cx.typeck_results().node_args(expr.hir_id),
+        )
+    }
+
+    /// Report a trait method that runs the trait's default body on a type of the
+    /// built-in table, e.g. `writeln!(lock, ..)` with `lock: StdoutLock`.
+    /// Returns whether the table classified the call.
+    fn check_inherited_call(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        expr: &hir::Expr<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> bool {
+        let args = cx.typeck_results().node_args(expr.hir_id);
+        let Some(path) = knowledge::inherited_path(cx.tcx, def_id, args) else {
+            return false;
+        };
+        // Allocations are left to REALTIME_ALLOCATES
+        let Some(api) = knowledge::lookup(&path).filter(|api| api.category != Category::Alloc)
+        else {
+            return false;
+        };
+        let message = format!(
+            "realtime function calls `{}` on `{}`, which is non-realtime because: {}",
+            cx.tcx.def_path_str(def_id),
+            args.type_at(0).peel_refs(),
+            api.reason
+        );
+        eprintln_ts!("[dylint] inherited nonrealtime call detected: {}", path);
+        let lint = self.category_lint(Some(api.category));
+        cx.span_lint(lint, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            Self::help_category(diag, Some(api.category), &path);
+        });
+        self.record(
+            cx,
+            lint,
+            &message,
+            CallKind::Method,
+            vec![(path, span)],
+            Some(api.reason.to_string()),
+        );
+        true
+    }
+
+    /// Explain why the current body is realtime when it is not marked itself
+    fn note_origin(&self, cx: &LateContext<'_>, diag: &mut Diag<'_, ()>) {
+        let Some(ctx) = self.realtime_context() else {
+            return;
+        };
+        let name = cx.tcx.def_path_str(ctx.def_id.to_def_id());
+        match ctx.origin {
+            Origin::Marked => {}
+            Origin::Contract(contract) => {
+                diag.span_note(
+                    cx.tcx.def_span(contract),
+                    format!(
+                        "`{}` must be realtime: it implements `{}`, which is marked realtime",
+                        name,
+                        cx.tcx.def_path_str(contract)
+                    ),
+                );
+            }
+            Origin::PassedTo(callee) => {
+                diag.span_note(
+                    cx.tcx.def_span(ctx.def_id),
+                    format!(
+                        "this closure runs in realtime code: it is passed to `{}`, which is realtime",
+                        cx.tcx.def_path_str(callee)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Report an impl marked non-realtime that implements a realtime trait method
+    fn check_contract_opt_out(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+        contract: rustc_hir::def_id::DefId,
+    ) {
+        let span = cx.tcx.def_span(def_id);
+        let message = format!(
+            "`{}` is marked non-realtime but implements `{}`, which is marked realtime",
+            cx.tcx.def_path_str(def_id),
+            cx.tcx.def_path_str(contract)
+        );
+        eprintln_ts!("[dylint] trait contract violation: {}", message);
+        cx.span_lint(REALTIME_IMPL_VIOLATES_CONTRACT, span, |diag| {
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            diag.span_note(
+                cx.tcx.def_span(contract),
+                "the realtime contract is declared here",
             );
+        });
+        self.record(
+            cx,
+            REALTIME_IMPL_VIOLATES_CONTRACT,
+            &message,
+            CallKind::Method,
+            vec![(cx.tcx.def_path_str(contract), span)],
+            None,
+        );
+    }
+
+    /// Classification of a function of another crate, by its marker,
+    /// dylint.toml, the built-in table and then its trait contract
+    fn external_is_realtime(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<Realtime> {
+        // External crate: the markers injected by rt_attrs are encoded in the
+        // upstream crate metadata, so read them back through `get_all_attrs`
+        let mut is_rt = Self::doc_marker_is_realtime(cx, cx.tcx.get_all_attrs(def_id));
+        let path = knowledge::canonical_path(cx.tcx, def_id);
+        // Unmarked: consult the classification from dylint.toml
+        if is_rt.is_none() {
+            if let Some(rule) = self.config.external_rule(&path) {
+                is_rt = Some(match rule.class {
+                    Class::Realtime => Realtime::Yes,
+                    Class::NonRealtime => Realtime::No(rule.reason.clone()),
+                });
+            }
+        }
+        // Then the built-in table of std APIs. Allocating APIs are left to
+        // REALTIME_ALLOCATES.
+        if is_rt.is_none() {
+            if let Some(api) = knowledge::lookup(&path) {
+                if api.category != Category::Alloc {
+                    is_rt = Some(Realtime::No(Some(api.reason.to_string())));
+                }
+            }
+        }
+        // Unclassified impls of realtime trait methods are held to the contract
+        if is_rt.is_none() && self.realtime_contract(cx, def_id).is_some() {
+            is_rt = Some(Realtime::Yes);
+        }
+        is_rt
+    }
+
+    /// Search the call graph below an unannotated local function for a call that
+    /// reaches a non-realtime function. Returns the call sites of the offending
+    /// chain, the last one being the call of the non-realtime function, along
+    /// with that function's classification.
+    fn nonrealtime_path(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<(Vec<CallSite>, Realtime)> {
+        let mut visited = HashSet::new();
+        self.nonrealtime_path_inner(cx, def_id, &mut visited)
+    }
+
+    fn nonrealtime_path_inner(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+        visited: &mut HashSet<rustc_hir::def_id::DefId>,
+    ) -> Option<(Vec<CallSite>, Realtime)> {
+        // Only unannotated local bodies are looked through; marked functions are
+        // trusted (or reported) as they are
+        let local = def_id.as_local().filter(|l| self.call_graph.contains(*l))?;
+        if !visited.insert(def_id) {
             return None;
         }
+        for site in self.calls_and_destructors(cx, local) {
+            match self.callee_is_realtime(cx, site.callee) {
+                Some(rt @ Realtime::No(_)) => return Some((vec![site], rt)),
+                Some(Realtime::Yes) => {}
+                None => {
+                    if let Some((mut path, rt)) =
+                        self.nonrealtime_path_inner(cx, site.callee, visited)
+                    {
+                        path.insert(0, site);
+                        return Some((path, rt));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Search the unannotated local functions below `def_id`, `def_id` included,
+    /// for one that allocates or frees heap memory. Returns the call sites
+    /// leading to that function, none if it is `def_id` itself, along with the
+    /// heap operation.
+    fn heap_path(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+    ) -> Option<(Vec<CallSite>, HeapOp)> {
+        let mut visited = HashSet::new();
+        self.heap_path_inner(cx, def_id, &mut visited)
+    }
+
+    fn heap_path_inner(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+        visited: &mut HashSet<rustc_hir::def_id::DefId>,
+    ) -> Option<(Vec<CallSite>, HeapOp)> {
+        let local = def_id.as_local().filter(|l| self.call_graph.contains(*l))?;
+        if !visited.insert(def_id) {
+            return None;
+        }
+        if let Some(heap) = Self::heap_op(cx, local) {
+            return Some((Vec::new(), heap));
+        }
+        for site in self.calls_and_destructors(cx, local) {
+            if self.callee_is_realtime(cx, site.callee).is_some() {
+                continue;
+            }
+            if let Some((mut path, heap)) = self.heap_path_inner(cx, site.callee, visited) {
+                path.insert(0, site);
+                return Some((path, heap));
+            }
+        }
+        None
+    }
+
+    /// The first allocation in the body of `def_id`, or else the first drop
+    /// freeing heap memory
+    fn heap_op(cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) -> Option<HeapOp> {
+        if let Some(alloc) = allocations::find_allocations(cx, def_id).into_iter().next() {
+            return Some(HeapOp {
+                span: alloc.span,
+                action: "allocates",
+                callee: alloc.what.trim_matches('`').to_string(),
+                what: format!("{} ({})", alloc.what, alloc.reason),
+                reason: "heap allocation",
+            });
+        }
+        drops::find_drops(cx, def_id).into_iter().find_map(|site| {
+            let owner = site.causes.iter().find_map(|cause| match cause {
+                drops::DropCause::Dealloc(owner) => Some(owner.clone()),
+                drops::DropCause::Destructor(_) => None,
+            })?;
+            Some(HeapOp {
+                span: site.span,
+                action: "frees heap memory",
+                what: format!("drops a `{}`", site.ty),
+                callee: owner,
+                reason: "heap deallocation",
+            })
+        })
+    }
+
+    /// The calls of an unannotated local body, followed by the destructors its
+    /// drops run, which count as calls too
+    fn calls_and_destructors(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_span::def_id::LocalDefId,
+    ) -> Vec<CallSite> {
+        let destructors = drops::find_drops(cx, def_id)
+            .into_iter()
+            .flat_map(|site| {
+                site.causes
+                    .into_iter()
+                    .filter_map(move |cause| match cause {
+                        drops::DropCause::Destructor(callee) => Some(CallSite {
+                            callee,
+                            span: site.span,
+                        }),
+                        drops::DropCause::Dealloc(_) => None,
+                    })
+            })
+            .collect::<Vec<_>>();
+        self.call_graph
+            .calls_of(def_id)
+            .iter()
+            .copied()
+            .chain(destructors)
+            .collect()
+    }
+
+    /// Add an emitted violation to the reports. `chain` holds the calls from the
+    /// realtime function to the offending callee, each with its call site: the
+    /// call in the realtime function first, then the calls made by the
+    /// unannotated functions in between, if any.
+    fn record(
+        &self,
+        cx: &LateContext<'_>,
+        lint: &'static Lint,
+        message: &str,
+        kind: CallKind,
+        chain: Vec<(String, Span)>,
+        reason: Option<String>,
+    ) {
+        // Counted for REALTIME_FN_NOT_REALTIME whatever the level of `lint`
+        if let (Some(ctx), Some(work), Some((_, span))) = (
+            self.realtime_context(),
+            Self::work_kind(lint),
+            chain.first(),
+        ) {
+            self.violations
+                .borrow_mut()
+                .entry(ctx.def_id)
+                .or_default()
+                .push((*span, work));
+        }
+        // Allowed (or expected) lints are not emitted, so they are not reported either
+        let level = cx
+            .tcx
+            .lint_level_at_node(lint, cx.last_node_with_lint_attrs)
+            .0;
+        if matches!(level, Level::Allow | Level::Expect(_)) {
+            return;
+        }
+        // Outside realtime code, the function whose marker is violated
+        let caller = self
+            .realtime_context()
+            .map(|ctx| ctx.def_id)
+            .or_else(|| self.body_stack.last().map(|frame| frame.body))
+            .map(|def_id| cx.tcx.def_path_str(def_id.to_def_id()))
+            .unwrap_or_default();
+        let location = |span: Span| {
+            let loc = cx.sess().source_map().lookup_char_pos(span.lo());
+            Location {
+                file: loc.file.name.prefer_local().to_string(),
+                line: loc.line,
+                column: loc.col_display + 1,
+            }
+        };
+        let mut hop_caller = caller.clone();
+        let flow = chain
+            .iter()
+            .map(|(callee, span)| {
+                let message = format!("`{}` calls `{}`", hop_caller, callee);
+                hop_caller = callee.clone();
+                Step {
+                    message,
+                    location: location(*span),
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut names = chain.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let callee = names.pop().unwrap_or_default();
+        self.report.record(Violation {
+            lint: lint.name_lower(),
+            level: report::sarif_level(level),
+            message: message.to_string(),
+            caller,
+            callee,
+            kind,
+            reason,
+            via: names,
+            location: flow[0].location.clone(),
+            flow,
+        });
+    }
+
+    /// Report heap allocations in the MIR of a realtime body
+    fn check_allocations(&self, cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) {
+        for alloc in allocations::find_allocations(cx, def_id) {
+            eprintln_ts!("[dylint] allocation detected: {}", alloc.what);
+            let message = format!(
+                "realtime function allocates: {} ({})",
+                alloc.what, alloc.reason
+            );
+            cx.span_lint(REALTIME_ALLOCATES, alloc.span, |diag| {
+                diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            });
+            self.record(
+                cx,
+                REALTIME_ALLOCATES,
+                &message,
+                CallKind::Fn,
+                vec![(alloc.what.trim_matches('`').to_string(), alloc.span)],
+                Some(alloc.reason.to_string()),
+            );
+        }
+    }
+
+    /// Summarize the violations found in the body of a function marked realtime
+    /// at its definition
+    fn check_marker_kept(&self, cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) {
+        let Some(mut violations) = self.violations.borrow_mut().remove(&def_id) else {
+            return;
+        };
+        // A call reaching several non-realtime functions of a kind is one place
+        let mut seen = HashSet::new();
+        violations.retain(|(span, work)| seen.insert((*span, *work)));
+        let name = cx.tcx.def_path_str(def_id.to_def_id());
+        eprintln_ts!(
+            "[dylint] realtime fn {} has {} violation(s)",
+            name,
+            violations.len()
+        );
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for (_, work) in &violations {
+            match counts.iter_mut().find(|(kind, _)| kind == work) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((work, 1)),
+            }
+        }
+        let summary = counts
+            .iter()
+            .map(|(kind, n)| match n {
+                1 => format!("1 {}", kind),
+                n => format!("{} {}s", n, kind),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = format!(
+            "`{}` is marked realtime but does non-realtime work: {}",
+            name, summary
+        );
+        let span = cx
+            .tcx
+            .def_ident_span(def_id)
+            .unwrap_or_else(|| cx.tcx.def_span(def_id));
+        cx.span_lint(REALTIME_FN_NOT_REALTIME, span, |diag| {
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+        });
+        self.record(
+            cx,
+            REALTIME_FN_NOT_REALTIME,
+            &message,
+            CallKind::Fn,
+            vec![(name, span)],
+            None,
+        );
+    }
+
+    /// What a violation reported by `lint` counts as in the summary of
+    /// REALTIME_FN_NOT_REALTIME. Strict mode and panic findings are not
+    /// non-realtime work.
+    fn work_kind(lint: &'static Lint) -> Option<&'static str> {
+        [
+            (REALTIME_CALLS_NONREALTIME, "non-realtime call"),
+            (REALTIME_IMPL_VIOLATES_CONTRACT, "non-realtime call"),
+            (REALTIME_ALLOCATES, "heap operation"),
+            (REALTIME_LOCKS, "blocking lock"),
+            (REALTIME_IO, "I/O call"),
+        ]
+        .into_iter()
+        .find(|(known, _)| std::ptr::eq(*known, lint))
+        .map(|(_, work)| work)
+    }
+
+    /// Report values dropped in a realtime body whose drop glue frees heap
+    /// memory or runs a non-realtime destructor
+    fn check_drops(&self, cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) {
+        for site in drops::find_drops(cx, def_id) {
+            let value = match &site.var {
+                Some(var) => format!("`{}` (`{}`)", var, site.ty),
+                None => format!("a `{}`", site.ty),
+            };
+            // One report for the memory freed, at the outermost owner
+            if let Some(owner) = site.causes.iter().find_map(|cause| match cause {
+                drops::DropCause::Dealloc(owner) => Some(owner),
+                drops::DropCause::Destructor(_) => None,
+            }) {
+                eprintln_ts!("[dylint] deallocation detected: {}", site.ty);
+                let message = format!("realtime function drops {}, which frees heap memory", value);
+                cx.span_lint(REALTIME_ALLOCATES, site.span, |diag| {
+                    diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+                    if *owner != site.ty {
+                        diag.note(format!("the memory is owned by a `{}`", owner));
+                    }
+                });
+                self.record(
+                    cx,
+                    REALTIME_ALLOCATES,
+                    &message,
+                    CallKind::Drop,
+                    vec![(owner.clone(), site.span)],
+                    Some("heap deallocation".to_string()),
+                );
+            }
+            for cause in &site.causes {
+                let drops::DropCause::Destructor(dtor) = cause else {
+                    continue;
+                };
+                self.check_classified(
+                    cx,
+                    CallSite {
+                        callee: *dtor,
+                        span: site.span,
+                    },
+                    CallKind::Drop,
+                    true,
+                    |name, _| {
+                        eprintln_ts!("[dylint] nonrealtime destructor detected: {}", name);
+                        format!(
+                            "realtime function drops {}, which runs non-realtime `{}`",
+                            value, name
+                        )
+                    },
+                    |diag, name, rt| {
+                        diag.span_note(
+                            cx.tcx.def_span(*dtor),
+                            format!("`{}` is defined here", name),
+                        );
+                        if let Realtime::No(Some(reason)) = rt {
+                            diag.note(format!("`{}` is non-realtime because: {}", name, reason));
+                        }
+                    },
+                );
+            }
+        }
+    }
 
-        let hir_id = cx.tcx.local_def_id_to_hir_id(def_id.expect_local());
-        let attrs = cx.tcx.hir().attrs(hir_id);
-        // eprintln!("Local DefId: {:?}, Path: {}", def_id, cx.tcx.def_path_str(def_id));
-        // eprintln!("Attrs: {:?}", attrs);
+    /// Report the calls the compiler inserts in a realtime body (overloaded
+    /// operators, dereferences, indexing, `?` and `for` loops) that run
+    /// non-realtime code
+    fn check_implicit_calls(&self, cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) {
+        for call in implicit::find_implicit_calls(cx.tcx, def_id) {
+            self.check_classified(
+                cx,
+                CallSite {
+                    callee: call.callee,
+                    span: call.span,
+                },
+                CallKind::Implicit,
+                // Strict mode already reports the trait method of calls the
+                // HIR pass sees
+                !call.hir_visible,
+                |name, rt| {
+                    eprintln_ts!("[dylint] implicit nonrealtime call detected: {}", name);
+                    format!("realtime function {}", rt.describe_call(name))
+                },
+                |diag, name, _| {
+                    diag.note(format!(
+                        "`{}` is called implicitly by {}",
+                        name,
+                        call.kind.describe()
+                    ));
+                },
+            );
+        }
+    }
 
-        Self::doc_marker_is_realtime(cx, attrs)
+    /// Report the panic paths of a realtime body, counted at the function
+    fn check_panics(&self, cx: &LateContext<'_>, def_id: rustc_span::def_id::LocalDefId) {
+        // Opt-in: spare the MIR walk while the lint is allowed
+        let level = cx
+            .tcx
+            .lint_level_at_node(REALTIME_PANICS, cx.last_node_with_lint_attrs)
+            .0;
+        if matches!(level, Level::Allow) {
+            return;
+        }
+        let sites = panics::find_panics(cx, def_id);
+        if sites.is_empty() {
+            return;
+        }
+        let name = cx.tcx.def_path_str(def_id.to_def_id());
+        let mut counts: Vec<(panics::PanicKind, usize)> = Vec::new();
+        for site in &sites {
+            match counts.iter_mut().find(|(kind, _)| *kind == site.kind) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((site.kind, 1)),
+            }
+        }
+        counts.sort();
+        let summary = counts
+            .iter()
+            .map(|(kind, count)| kind.describe(*count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        eprintln_ts!("[dylint] panic paths in {}: {}", name, summary);
+        let message = format!(
+            "`{}` may panic in {} place{}: {}",
+            name,
+            sites.len(),
+            if sites.len() == 1 { "" } else { "s" },
+            summary
+        );
+        let span = cx
+            .tcx
+            .def_ident_span(def_id)
+            .unwrap_or_else(|| cx.tcx.def_span(def_id));
+        cx.span_lint(REALTIME_PANICS, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            for site in &sites {
+                diag.span_note(site.span, format!("{} may panic here", site.what));
+            }
+        });
+        for site in sites {
+            self.record(
+                cx,
+                REALTIME_PANICS,
+                &format!("realtime function may panic: {}", site.what),
+                CallKind::Fn,
+                vec![(site.what.trim_matches('`').to_string(), site.span)],
+                None,
+            );
+        }
+    }
+
+    /// Report a call of a non-realtime function, directly or through unannotated ones
+    fn check_call(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        kind: CallKind,
+        def_id: rustc_hir::def_id::DefId,
+    ) {
+        self.check_classified(
+            cx,
+            CallSite {
+                callee: def_id,
+                span,
+            },
+            kind,
+            true,
+            |name, rt| format!("realtime function {}", rt.describe_call(name)),
+            |_, _, _| {},
+        );
+    }
+
+    /// Classify the call at `site` and report it: a non-realtime callee by its
+    /// lint, with the message `describe` gives for the callee's name and the
+    /// notes `annotate` adds; with `strict`, a callee nobody vouched for in
+    /// strict mode; an unclassified callee through what it reaches
+    fn check_classified(
+        &self,
+        cx: &LateContext<'_>,
+        site: CallSite,
+        kind: CallKind,
+        strict: bool,
+        describe: impl FnOnce(&str, &Realtime) -> String,
+        annotate: impl Fn(&mut Diag<'_, ()>, &str, &Realtime),
+    ) {
+        match self.callee_is_realtime(cx, site.callee) {
+            Some(rt @ Realtime::No(_)) => {
+                let name = cx.tcx.def_path_str(site.callee);
+                let message = describe(&name, &rt);
+                let lint = self.lint_for(cx, site.callee);
+                cx.span_lint(lint, site.span, |diag| {
+                    self.note_origin(cx, diag);
+                    diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+                    annotate(diag, &name, &rt);
+                    self.help_alternative(cx, diag, site.callee);
+                });
+                self.record(
+                    cx,
+                    lint,
+                    &message,
+                    kind,
+                    vec![(name, site.span)],
+                    rt.reason(),
+                );
+            }
+            rt => {
+                if strict && self.unvouched(cx, site.callee) {
+                    self.check_unannotated(cx, site.span, kind, site.callee);
+                }
+                if rt.is_none() {
+                    self.check_transitive(cx, site.span, kind, site.callee);
+                }
+            }
+        }
+    }
+
+    /// Whether strict mode reports a call to `def_id`, which nobody vouched
+    /// for (inferred-safe functions included). Constructors run no code, and
+    /// allocating std APIs are classified by REALTIME_ALLOCATES.
+    fn unvouched(&self, cx: &LateContext<'_>, def_id: rustc_hir::def_id::DefId) -> bool {
+        if !self.config.strict
+            || matches!(cx.tcx.def_kind(def_id), DefKind::Ctor(..))
+            || self.vouched_class(cx, def_id).is_some()
+        {
+            return false;
+        }
+        knowledge::lookup(&knowledge::canonical_path(cx.tcx, def_id)).is_none()
+            && !self.config.strict_allows(&Self::strict_path(cx, def_id))
+    }
+
+    /// Path `strict_allow` patterns are matched against: the canonical path,
+    /// prefixed with the crate name for local functions (`my_app::helper`)
+    fn strict_path(cx: &LateContext<'_>, def_id: rustc_hir::def_id::DefId) -> String {
+        let path = knowledge::canonical_path(cx.tcx, def_id);
+        match def_id.as_local() {
+            Some(_) => format!(
+                "{}::{}",
+                cx.tcx.crate_name(rustc_span::def_id::LOCAL_CRATE),
+                path
+            ),
+            None => path,
+        }
+    }
+
+    /// Report a call to an unannotated function in strict mode
+    fn check_unannotated(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        kind: CallKind,
+        def_id: rustc_hir::def_id::DefId,
+    ) {
+        let callee = cx.tcx.def_path_str(def_id);
+        eprintln_ts!("[dylint] unannotated call detected: {}", callee);
+        let message = format!("realtime function calls unannotated `{}`", callee);
+        cx.span_lint(REALTIME_CALLS_UNANNOTATED, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            diag.help(format!(
+                "mark `{}` `#[realtime]` or `#[non_realtime]`, or allow `{}` with `strict_allow`",
+                callee,
+                Self::strict_path(cx, def_id)
+            ));
+        });
+        self.record(
+            cx,
+            REALTIME_CALLS_UNANNOTATED,
+            &message,
+            kind,
+            vec![(callee, span)],
+            None,
+        );
+    }
+
+    /// In strict mode, report a call through a fn pointer none of whose targets
+    /// are known, e.g. a parameter
+    fn check_unknown_fnptr(&self, cx: &LateContext<'_>, span: Span, pointer: String) {
+        eprintln_ts!("[dylint] fn-ptr call without known targets: {}", pointer);
+        let message = format!(
+            "realtime function calls `{}`, which may point to any function",
+            pointer
+        );
+        cx.span_lint(REALTIME_CALLS_UNANNOTATED, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            diag.note(format!(
+                "no function stored in `{}` is known, so none of them is vouched for",
+                pointer
+            ));
+        });
+        self.record(
+            cx,
+            REALTIME_CALLS_UNANNOTATED,
+            &message,
+            CallKind::FnPtr,
+            vec![(pointer, span)],
+            None,
+        );
+    }
+
+    /// Report a method call on a `dyn Trait` when any implementation it may
+    /// dispatch to is non-realtime, directly or through unannotated functions
+    fn check_dyn_call(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        trait_id: rustc_hir::def_id::DefId,
+        def_id: rustc_hir::def_id::DefId,
+    ) {
+        let implementations = dispatch::implementations(cx.tcx, trait_id, def_id);
+        self.check_dyn_unannotated(cx, span, trait_id, def_id, &implementations);
+        let offending = implementations
+            .into_iter()
+            .filter_map(|imp| match self.callee_is_realtime(cx, imp) {
+                Some(rt @ Realtime::No(_)) => Some((imp, Vec::new(), rt)),
+                Some(Realtime::Yes) => None,
+                None => self
+                    .nonrealtime_path(cx, imp)
+                    .map(|(path, rt)| (imp, path, rt)),
+            })
+            .collect::<Vec<_>>();
+        if offending.is_empty() {
+            return;
+        }
+        let message = format!(
+            "realtime function calls `{}` on `dyn {}`, which may run non-realtime {}",
+            cx.tcx.def_path_str(def_id),
+            cx.tcx.def_path_str(trait_id),
+            offending
+                .iter()
+                .map(|(imp, _, _)| format!("`{}`", cx.tcx.def_path_str(*imp)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        eprintln_ts!("[dylint] nonrealtime dyn call detected: {}", message);
+        // The category of the non-realtime function each implementation reaches
+        let categories = offending
+            .iter()
+            .map(|(imp, path, _)| {
+                self.builtin_category(cx, path.last().map_or(*imp, |last| last.callee))
+            })
+            .collect::<Vec<_>>();
+        // One category lints the call by its own lint, a mix as a plain call
+        let shared = categories
+            .iter()
+            .all(|category| *category == categories[0])
+            .then_some(categories[0])
+            .flatten();
+        cx.span_lint(self.category_lint(shared), span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            // One note per offending implementation, at its definition
+            for (imp, path, rt) in &offending {
+                let imp_name = cx.tcx.def_path_str(*imp);
+                let note = match (path.last(), rt) {
+                    (None, Realtime::No(Some(reason))) => {
+                        format!("`{}` is non-realtime because: {}", imp_name, reason)
+                    }
+                    (None, _) => format!("`{}` is non-realtime", imp_name),
+                    (Some(last), _) => format!(
+                        "`{}` reaches non-realtime `{}`",
+                        imp_name,
+                        cx.tcx.def_path_str(last.callee)
+                    ),
+                };
+                diag.span_note(cx.tcx.def_span(*imp), note);
+            }
+            if let (Some(_), Some((imp, path, _))) = (shared, offending.first()) {
+                self.help_alternative(cx, diag, path.last().map_or(*imp, |last| last.callee));
+            }
+        });
+        for ((imp, path, rt), category) in offending.into_iter().zip(categories) {
+            let hops = std::iter::once((imp, span))
+                .chain(path.iter().map(|site| (site.callee, site.span)))
+                .map(|(did, span)| (cx.tcx.def_path_str(did), span))
+                .collect();
+            self.record(
+                cx,
+                self.category_lint(category),
+                &message,
+                CallKind::Method,
+                hops,
+                rt.reason(),
+            );
+        }
+    }
+
+    /// In strict mode, report a method call on a `dyn Trait` when any
+    /// implementation it may dispatch to is unannotated
+    fn check_dyn_unannotated(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        trait_id: rustc_hir::def_id::DefId,
+        def_id: rustc_hir::def_id::DefId,
+        implementations: &[rustc_hir::def_id::DefId],
+    ) {
+        let unannotated = implementations
+            .iter()
+            .copied()
+            .filter(|imp| self.unvouched(cx, *imp))
+            .collect::<Vec<_>>();
+        if unannotated.is_empty() {
+            return;
+        }
+        let message = format!(
+            "realtime function calls `{}` on `dyn {}`, which may run unannotated {}",
+            cx.tcx.def_path_str(def_id),
+            cx.tcx.def_path_str(trait_id),
+            unannotated
+                .iter()
+                .map(|imp| format!("`{}`", cx.tcx.def_path_str(*imp)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        eprintln_ts!("[dylint] unannotated dyn call detected: {}", message);
+        cx.span_lint(REALTIME_CALLS_UNANNOTATED, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            for imp in &unannotated {
+                diag.span_note(
+                    cx.tcx.def_span(*imp),
+                    format!("`{}` is not annotated", cx.tcx.def_path_str(*imp)),
+                );
+            }
+        });
+        for imp in unannotated {
+            self.record(
+                cx,
+                REALTIME_CALLS_UNANNOTATED,
+                &message,
+                CallKind::Method,
+                vec![(cx.tcx.def_path_str(imp), span)],
+                None,
+            );
+        }
+    }
+
+    /// Report non-realtime functions reached through generic or trait calls once
+    /// the generic arguments are known (the `monomorphize` setting)
+    fn check_generic_calls<'tcx>(&self, cx: &LateContext<'tcx>, def_id: rustc_hir::def_id::DefId) {
+        let classify = |did| self.callee_is_realtime(cx, did);
+        for finding in mono::find_generic_violations(cx.tcx, def_id, &classify) {
+            let name = |call: &mono::MonoCall<'tcx>| {
+                cx.tcx
+                    .def_path_str_with_args(call.resolved.def_id(), call.resolved.args)
+            };
+            let chain = std::iter::once(cx.tcx.def_path_str(def_id))
+                .chain(finding.calls.iter().map(name))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            eprintln_ts!("[dylint] generic nonrealtime call detected: {}", chain);
+            let span = finding.calls[0].span;
+            let last = finding.calls[finding.calls.len() - 1];
+            let message = format!(
+                "nonrealtime function reached through generic call: {}",
+                chain
+            );
+            let lint = self.lint_for(cx, last.resolved.def_id());
+            cx.span_lint(lint, span, |diag| {
+                self.note_origin(cx, diag);
+                diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+                // One note per call, with the substitution it was resolved with
+                let mut caller = cx.tcx.def_path_str(def_id);
+                for call in &finding.calls {
+                    let substitution = mono::substitution(cx.tcx, call.callee, call.args);
+                    let resolved = name(call);
+                    let note = if substitution.is_empty() {
+                        format!("`{}` calls `{}` here", caller, resolved)
+                    } else {
+                        format!(
+                            "`{}` calls `{}` here, with `{}`, resolving to `{}`",
+                            caller,
+                            cx.tcx.def_path_str(call.callee),
+                            substitution,
+                            resolved
+                        )
+                    };
+                    diag.span_note(call.span, note);
+                    caller = resolved;
+                }
+                if let Realtime::No(Some(reason)) = &finding.rt {
+                    diag.note(format!(
+                        "`{}` is non-realtime because: {}",
+                        name(&last),
+                        reason
+                    ));
+                }
+                self.help_alternative(cx, diag, last.resolved.def_id());
+            });
+            let hops = finding
+                .calls
+                .iter()
+                .map(|call| (name(call), call.span))
+                .collect();
+            self.record(cx, lint, &message, CallKind::Fn, hops, finding.rt.reason());
+        }
+    }
+
+    /// Report a call of a closure marked `#[rt_call_info("closure", "nonrealtime")]`
+    fn check_closure_call(&self, cx: &LateContext<'_>, span: Span, callee: &hir::Expr<'_>) {
+        let var_name = cx
+            .sess()
+            .source_map()
+            .span_to_snippet(callee.span)
+            .unwrap_or_else(|_| "closure".to_string());
+        eprintln_ts!("[dylint] nonrealtime closure call detected: {}", var_name);
+        let message = format!("Nonrealtime Closure call detected: {}", var_name);
+        let lint = match cx.typeck_results().expr_ty(callee).kind() {
+            ty::Closure(closure, _) => self.lint_for(cx, *closure),
+            _ => self.category_lint(None),
+        };
+        cx.span_lint(lint, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+        });
+        self.record(
+            cx,
+            lint,
+            &message,
+            CallKind::Closure,
+            vec![(var_name, span)],
+            None,
+        );
+    }
+
+    /// Report a call through a fn pointer (`fp()`, `(self.cb)()`, `TABLE[i]()`,
+    /// `cb.unwrap()()`) for every non-realtime function that may be stored in it
+    fn check_fnptr_call(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        call: &hir::Expr<'_>,
+        callee: &hir::Expr<'_>,
+    ) {
+        let pointer = cx
+            .sess()
+            .source_map()
+            .span_to_snippet(callee.span)
+            .map(|snippet| {
+                // `(self.cb)()` is written with parentheses around the field
+                match snippet.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+                    Some(inner) => inner.to_string(),
+                    None => snippet,
+                }
+            })
+            .unwrap_or_else(|_| "fn pointer".to_string());
+        // Variables of the body are resolved at the call, anything else crate-wide
+        let targets = match self.local_fnptr_targets.get(&call.hir_id) {
+            Some(targets) => targets.clone(),
+            None => self.fnptr_flow.targets_of(cx.typeck_results(), callee),
+        };
+        // Nothing is known of what a parameter or the result of a call holds
+        if targets.is_empty() && self.config.strict {
+            self.check_unknown_fnptr(cx, span, pointer.clone());
+        }
+        for target in targets {
+            self.check_classified(
+                cx,
+                CallSite {
+                    callee: target,
+                    span,
+                },
+                CallKind::FnPtr,
+                true,
+                |name, _| {
+                    eprintln_ts!("[dylint] nonrealtime fn-ptr target detected: {}", name);
+                    format!(
+                        "realtime function calls `{}`, which may point to non-realtime `{}`",
+                        pointer, name
+                    )
+                },
+                |diag, name, rt| {
+                    if target.is_local() {
+                        diag.span_note(
+                            cx.tcx.def_span(target),
+                            format!("`{}` is defined here", name),
+                        );
+                    }
+                    if let Realtime::No(Some(reason)) = rt {
+                        diag.note(format!("`{}` is non-realtime because: {}", name, reason));
+                    }
+                },
+            );
+        }
+    }
+
+    /// Report a call to an unannotated function that transitively reaches a
+    /// non-realtime one, or else allocates or frees heap memory
+    fn check_transitive(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        kind: CallKind,
+        def_id: rustc_hir::def_id::DefId,
+    ) {
+        let Some((path, rt)) = self.nonrealtime_path(cx, def_id) else {
+            self.check_transitive_heap(cx, span, kind, def_id);
+            return;
+        };
+        // realtime_fn -> helper -> ... -> non_realtime_fn
+        let chain = self.transitive_chain(cx, def_id, &path);
+        eprintln_ts!("[dylint] transitive nonrealtime call detected: {}", chain);
+        let last = path[path.len() - 1];
+        let message = format!(
+            "nonrealtime function reached through unannotated call: {}",
+            chain
+        );
+        let lint = self.lint_for(cx, last.callee);
+        cx.span_lint(lint, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            Self::note_hops(cx, diag, def_id, &path);
+            if let Realtime::No(Some(reason)) = &rt {
+                diag.note(format!(
+                    "`{}` is non-realtime because: {}",
+                    cx.tcx.def_path_str(last.callee),
+                    reason
+                ));
+            }
+            self.help_alternative(cx, diag, last.callee);
+        });
+        let hops = std::iter::once((def_id, span))
+            .chain(path.iter().map(|site| (site.callee, site.span)))
+            .map(|(did, span)| (cx.tcx.def_path_str(did), span))
+            .collect();
+        self.record(cx, lint, &message, kind, hops, rt.reason());
+    }
+
+    /// Report a call to an unannotated function that allocates or frees heap
+    /// memory, itself or through the unannotated functions it calls
+    fn check_transitive_heap(
+        &self,
+        cx: &LateContext<'_>,
+        span: Span,
+        kind: CallKind,
+        def_id: rustc_hir::def_id::DefId,
+    ) {
+        let Some((path, heap)) = self.heap_path(cx, def_id) else {
+            return;
+        };
+        let chain = self.transitive_chain(cx, def_id, &path);
+        eprintln_ts!("[dylint] transitive heap operation detected: {}", chain);
+        let message = format!("heap operation reached through unannotated call: {}", chain);
+        let owner = path.last().map_or(def_id, |site| site.callee);
+        cx.span_lint(REALTIME_ALLOCATES, span, |diag| {
+            self.note_origin(cx, diag);
+            diag.primary_message(format!("{} {}", crate::timestamp_prefix(), message));
+            Self::note_hops(cx, diag, def_id, &path);
+            diag.span_note(
+                heap.span,
+                format!(
+                    "`{}` {} here: {}",
+                    cx.tcx.def_path_str(owner),
+                    heap.action,
+                    heap.what
+                ),
+            );
+        });
+        let hops = std::iter::once((def_id, span))
+            .chain(path.iter().map(|site| (site.callee, site.span)))
+            .map(|(did, span)| (cx.tcx.def_path_str(did), span))
+            .chain(std::iter::once((heap.callee, heap.span)))
+            .collect();
+        self.record(
+            cx,
+            REALTIME_ALLOCATES,
+            &message,
+            kind,
+            hops,
+            Some(heap.reason.to_string()),
+        );
+    }
+
+    /// `realtime_fn -> def_id -> ...` along the call sites of `path`
+    fn transitive_chain(
+        &self,
+        cx: &LateContext<'_>,
+        def_id: rustc_hir::def_id::DefId,
+        path: &[CallSite],
+    ) -> String {
+        let caller = self.realtime_context().map(|ctx| ctx.def_id.to_def_id());
+        caller
+            .into_iter()
+            .chain(std::iter::once(def_id))
+            .chain(path.iter().map(|site| site.callee))
+            .map(|did| cx.tcx.def_path_str(did))
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+
+    /// One note per hop of `path`, at the call site inside the calling function
+    fn note_hops(
+        cx: &LateContext<'_>,
+        diag: &mut Diag<'_, ()>,
+        def_id: rustc_hir::def_id::DefId,
+        path: &[CallSite],
+    ) {
+        let mut hop_caller = def_id;
+        for site in path {
+            diag.span_note(
+                site.span,
+                format!(
+                    "`{}` calls `{}` here",
+                    cx.tcx.def_path_str(hop_caller),
+                    cx.tcx.def_path_str(site.callee)
+                ),
+            );
+            hop_caller = site.callee;
+        }
     }
 }
 
 impl<'tcx> LateLintPass<'tcx> for RealtimeCallsNonrealtime {
+    fn check_crate(&mut self, cx: &LateContext<'tcx>) {
+        eprintln_ts!(
+            "[dylint] built-in knowledge base version {}",
+            knowledge::KNOWLEDGE_BASE_VERSION
+        );
+        self.call_graph = CallGraph::build(cx.tcx);
+        // Inferred from the markers alone, the inference being still empty
+        let inference = Inference::build(cx, &self.call_graph, &|did| {
+            self.callee_is_realtime(cx, did)
+        });
+        self.inference = inference;
+        self.inference.dump(cx, &self.config);
+        self.fnptr_flow = FnPtrFlow::build(cx.tcx);
+        self.realtime_closures = self
+            .call_graph
+            .closure_args()
+            .iter()
+            .filter(|arg| matches!(self.callee_is_realtime(cx, arg.callee), Some(Realtime::Yes)))
+            .map(|arg| (arg.closure, arg.callee))
+            .collect();
+    }
+
+    fn check_crate_post(&mut self, cx: &LateContext<'tcx>) {
+        let crate_name = cx.tcx.crate_name(rustc_span::def_id::LOCAL_CRATE);
+        self.report
+            .finish(crate_name.as_str(), &RealtimeCallsNonrealtime::lint_vec());
+    }
+
     fn check_fn(
         &mut self,
         cx: &LateContext<'tcx>,
         _fk: rustc_hir::intravisit::FnKind<'tcx>,
         _decl: &'tcx hir::FnDecl<'tcx>,
-        _body: &'tcx hir::Body<'tcx>,
+        body: &'tcx hir::Body<'tcx>,
         _span: Span,
         def_id: rustc_span::def_id::LocalDefId,
     ) {
         eprintln_ts!("[debug] check fn: {:?}", def_id);
-        // Skip closures directly
         let did = def_id.to_def_id();
-        if cx.tcx.def_kind(did) == DefKind::Closure {
-            return;
+        let is_closure = cx.tcx.def_kind(did) == DefKind::Closure;
+        let mut opt_out = None;
+        let realtime = if is_closure {
+            self.closure_context(def_id)
+        } else {
+            let hir_id = cx.tcx.local_def_id_to_hir_id(def_id);
+            let attrs = cx.tcx.hir_attrs(hir_id);
+            match Self::doc_marker_is_realtime(cx, attrs) {
+                Some(Realtime::Yes) => Some(RealtimeContext {
+                    def_id,
+                    origin: Origin::Marked,
+                }),
+                // An impl of a realtime trait method cannot opt out of the contract
+                Some(Realtime::No(_)) => {
+                    opt_out = self.realtime_contract(cx, did);
+                    None
+                }
+                // Unmarked impls inherit the contract of the trait method
+                None => self
+                    .realtime_contract(cx, did)
+                    .map(|contract| RealtimeContext {
+                        def_id,
+                        origin: Origin::Contract(contract),
+                    }),
+            }
+        };
+        self.body_stack.push(Frame {
+            body: def_id,
+            realtime,
+        });
+        if let Some(contract) = opt_out {
+            self.check_contract_opt_out(cx, did, contract);
+        }
+        if self.in_realtime() {
+            self.local_fnptr_targets
+                .extend(self.fnptr_flow.local_targets(cx.tcx.typeck(def_id), body));
+            self.check_allocations(cx, def_id);
+            self.check_implicit_calls(cx, def_id);
+            self.check_drops(cx, def_id);
+            self.check_panics(cx, def_id);
+            // Generic roots are only analyzed through their concrete callers
+            if self.config.monomorphize
+                && !is_closure
+                && !cx.tcx.generics_of(did).requires_monomorphization(cx.tcx)
+            {
+                self.check_generic_calls(cx, did);
+            }
         }
-        self.in_realtime_main_fn.clear();
-        let hir_id = cx.tcx.local_def_id_to_hir_id(def_id);
-        let attrs = cx.tcx.hir().attrs(hir_id);
-        if matches!(Self::doc_marker_is_realtime(cx, attrs), Some(true)) {
-            self.in_realtime_main_fn.push(hir_id);
+    }
+
+    fn check_body_post(&mut self, cx: &LateContext<'tcx>, body: &hir::Body<'tcx>) {
+        let def_id = cx.tcx.hir_body_owner_def_id(body.id());
+        // Only fn and closure bodies have a frame, not e.g. array lengths
+        if self
+            .body_stack
+            .last()
+            .is_some_and(|frame| frame.body == def_id)
+        {
+            // Summarized while the function is still the realtime context
+            if let Some(Frame {
+                realtime: Some(ctx),
+                ..
+            }) = self.body_stack.last()
+            {
+                if ctx.def_id == def_id
+                    && ctx.origin == Origin::Marked
+                    && cx.tcx.def_kind(def_id) != DefKind::Closure
+                {
+                    self.check_marker_kept(cx, def_id);
+                }
+            }
+            self.body_stack.pop();
         }
     }
 
     /// Parse closure markers at the statement level (let bindings)
     fn check_stmt(&mut self, cx: &LateContext<'tcx>, stmt: &'tcx hir::Stmt<'tcx>) {
         if let hir::StmtKind::Let(local) = stmt.kind {
-            let attrs = cx.tcx.hir().attrs(stmt.hir_id);
+            let attrs = cx.tcx.hir_attrs(stmt.hir_id);
             if let Some((name_marker, is_rt)) = Self::extract_call_info_from_doc_attrs(attrs) {
                 if name_marker == "closure" {
                     if let hir::PatKind::Binding(_, _hir_id, ident, _pat) = local.pat.kind {
-                        let var_name = ident.name.to_string();
-                        self.closure_var_realtime.insert(var_name.clone(), is_rt);
+                        if let Some(hir::Expr {
+                            kind: hir::ExprKind::Closure(closure),
+                            ..
+                        }) = local.init
+                        {
+                            self.closure_marks.insert(closure.def_id, is_rt);
+                        }
                         eprintln_ts!(
                             "[dylint] record closure var '{}' as realtime={}",
-                            var_name,
+                            ident.name,
                             is_rt
                         );
                     }
                 }
-                // } else {
-                //     // Treat as a function-pointer variable marker. Try to parse function DefId
-                //     // from the RHS and decide realtime based on doc attributes.
-                //     if let Some(init) = local.init {
-                //         if let hir::ExprKind::Path(qpath) = init.kind {
-                //             let res = cx.qpath_res(&qpath, init.hir_id);
-                //             if let Some(def_id) = res.opt_def_id() {
-                //                 let is_fn_rt = Self::callee_is_realtime(cx, def_id);
-                //                 if let hir::PatKind::Binding(_, _hid, ident, _pat) = local.pat.kind
-                //                 {
-                //                     let var_name = ident.name.to_string();
-                //                     self.fnptr_var_realtime.insert(var_name.clone(), is_fn_rt);
-                //                     eprintln!(
-                //                         "[dylint] record fn-ptr var '{}' -> target '{}' realtime={:?} (marker name='{}')",
-                //                         var_name,
-                //                         cx.tcx.def_path_str(def_id),
-                //                         is_fn_rt,
-                //                         name_marker,
-                //                     );
-                //                 }
-                //             }
-                //         }
-                //     }
-                // }
-            }
-
-            // Extra: without markers, directly detect function-pointer assignments in let (fn item/associated fn)
-            if let Some(init) = local.init {
-                if let hir::ExprKind::Path(qpath) = init.kind {
-                    let res = cx.qpath_res(&qpath, init.hir_id);
-                    if let Some(def_id) = res.opt_def_id() {
-                        let kind = cx.tcx.def_kind(def_id);
-                        if matches!(kind, DefKind::Fn | DefKind::AssocFn) {
-                            if let hir::PatKind::Binding(_, _hid, ident, _pat) = local.pat.kind {
-                                let var_name = ident.name.to_string();
-                                let is_fn_rt = Self::callee_is_realtime(cx, def_id);
-                                if let Some(is_fn_rt) = is_fn_rt {
-                                    self.fnptr_var_realtime.insert(var_name.clone(), is_fn_rt);
-                                }
-                                eprintln_ts!(
-                                    "[dylint] auto-detect fn-ptr var '{}' -> target '{}' realtime={:?}",
-                                    var_name,
-                                    cx.tcx.def_path_str(def_id),
-                                    is_fn_rt,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Propagation: let new = old; make new inherit old's realtime property (closure/fn-ptr)
-            if let Some(init) = local.init {
-                if let hir::ExprKind::Path(qpath) = init.kind {
-                    if let Res::Local(src_id) = cx.qpath_res(&qpath, init.hir_id) {
-                        if let Some(sym) = cx.tcx.hir().opt_name(src_id) {
-                            let src = sym.to_string();
-                            if let hir::PatKind::Binding(_, _hid, ident, _pat) = local.pat.kind {
-                                let dst = ident.name.to_string();
-                                if let Some(v) = self.closure_var_realtime.get(&src).copied() {
-                                    self.closure_var_realtime.insert(dst.clone(), v);
-                                    eprintln_ts!(
-                                        "[dylint] propagate closure realtime {} -> {} = {:?}",
-                                        src,
-                                        dst,
-                                        v
-                                    );
-                                }
-                                if let Some(v) = self.fnptr_var_realtime.get(&src).copied() {
-                                    self.fnptr_var_realtime.insert(dst.clone(), v);
-                                    eprintln_ts!(
-                                        "[dylint] propagate fn-ptr realtime {} -> {} = {:?}",
-                                        src,
-                                        dst,
-                                        v
-                                    );
-                                }
-                            }
-                        }
-                    }
-                }
             }
         }
     }
@@ -278,18 +2034,35 @@ impl<'tcx> LateLintPass<'tcx> for RealtimeCallsNonrealtime {
             return;
         }
         // eprintln!("[debug] in realtime");
+        // Calls expanded from macros (e.g. `println!`) are reported at the macro call,
+        // lints pointing into external macros would be dropped
+        let span = expr.span.source_callsite();
 
         // 1) Method call: foo.bar(...)
         //trait and method call
-        if let hir::ExprKind::MethodCall(_seg, _recv, _args, _fn_span) = expr.kind {
+        if let hir::ExprKind::MethodCall(_seg, recv, _args, _fn_span) = expr.kind {
             if let Some(def_id) = cx.typeck_results().type_dependent_def_id(expr.hir_id) {
-                if Self::callee_is_realtime(cx, def_id) == Some(false) {
-                    cx.span_lint(REALTIME_CALLS_NONREALTIME, expr.span, |diag| {
-                        diag.primary_message(format!(
-                            "{} realtime function calls a non-realtime method",
-                            crate::timestamp_prefix()
-                        ));
-                    });
+                match dispatch::dyn_trait_of_call(cx, recv, def_id) {
+                    // On a trait object, unless the trait method itself is
+                    // non-realtime, look at every implementation it may run
+                    Some(trait_id)
+                        if !matches!(
+                            self.callee_is_realtime(cx, def_id),
+                            Some(Realtime::No(_))
+                        ) =>
+                    {
+                        self.check_dyn_call(cx, span, trait_id, def_id)
+                    }
+                    _ => {
+                        let callee = self.concrete_callee(cx, expr, def_id);
+                        // A call the table classified is not unannotated
+                        let classified = cx.tcx.trait_of_item(callee).is_some()
+                            && self.callee_is_realtime(cx, callee).is_none()
+                            && self.check_inherited_call(cx, span, expr, callee);
+                        if !classified {
+                            self.check_call(cx, span, CallKind::Method, callee)
+                        }
+                    }
                 }
             }
         }
@@ -297,61 +2070,38 @@ impl<'tcx> LateLintPass<'tcx> for RealtimeCallsNonrealtime {
         // 2) Ordinary function call: path_fn(...)
         if let hir::ExprKind::Call(callee, _args) = expr.kind {
             // Resolve callee's DefId (handle common path forms)
+            let mut resolved = false;
             if let hir::ExprKind::Path(qpath) = callee.kind {
                 let res = cx.qpath_res(&qpath, callee.hir_id);
-                // A. Local variable (closure variable)
-                if let Res::Local(local_id) = res {
-                    if let Some(sym) = cx.tcx.hir().opt_name(local_id) {
-                        let var_name = sym.to_string();
-                        eprintln_ts!(
-                            "[debug] check closure var '{}' realtime={:?}",
-                            var_name,
-                            self.closure_var_realtime.get(&var_name).copied()
-                        );
-                        if let Some(is_rt) = self.closure_var_realtime.get(&var_name).copied() {
-                            if is_rt == false {
-                                eprintln_ts!(
-                                    "[dylint] nonrealtime closure call detected: {}",
-                                    var_name
-                                );
-                                cx.span_lint(REALTIME_CALLS_NONREALTIME, expr.span, |diag| {
-                                    diag.primary_message(format!(
-                                        "{} Nonrealtime Closure call detected: {}",
-                                        crate::timestamp_prefix(),
-                                        var_name
-                                    ));
-                                });
-                            }
-                        }
-                        if let Some(is_rt) = self.fnptr_var_realtime.get(&var_name).copied() {
-                            if is_rt == false {
-                                eprintln_ts!(
-                                    "[dylint] nonrealtime fn-ptr call detected: {}",
-                                    var_name
-                                );
-                                cx.span_lint(REALTIME_CALLS_NONREALTIME, expr.span, |diag| {
-                                    diag.primary_message(format!(
-                                        "{} Nonrealtime Fn-ptr call detected: {}",
-                                        crate::timestamp_prefix(),
-                                        var_name
-                                    ));
-                                });
+                // A. Ordinary function DefId
+                if let Some(def_id) = res.opt_def_id() {
+                    self.check_call(cx, span, CallKind::Fn, def_id);
+                    resolved = true;
+                }
+            }
+            // Calls through values: the type of the callee tells which closure
+            // or fn item a variable holds, however it was copied or shadowed
+            match cx.typeck_results().expr_ty(callee).kind() {
+                // B. Closure marked non-realtime by rt_call_info, or not realtime
+                // itself under the explicit policy: checked through what it does
+                ty::Closure(closure, _) => {
+                    if let Some(local) = closure.as_local() {
+                        match self.closure_marks.get(&local) {
+                            Some(false) => self.check_closure_call(cx, span, callee),
+                            None if self.closure_context(local).is_none() => {
+                                self.check_transitive(cx, span, CallKind::Closure, *closure)
                             }
+                            _ => {}
                         }
                     }
                 }
-                // B. Ordinary function DefId
-                if let Some(def_id) = res.opt_def_id() {
-                    if Self::callee_is_realtime(cx, def_id) == Some(false) {
-                        cx.span_lint(REALTIME_CALLS_NONREALTIME, expr.span, |diag| {
-                            diag.primary_message(format!(
-                                "{} nonrealtime function call detected: {}",
-                                crate::timestamp_prefix(),
-                                cx.tcx.def_path_str(def_id)
-                            ));
-                        });
-                    }
+                // C. Fn item held by a variable (`let f = print_rt; f()`)
+                ty::FnDef(def_id, _) if !resolved => {
+                    self.check_call(cx, span, CallKind::FnPtr, *def_id);
                 }
+                // D. Fn pointers: variables, fields, array elements, statics, `Option<fn()>`
+                ty::FnPtr(..) => self.check_fnptr_call(cx, span, expr, callee),
+                _ => {}
             }
         }
     }
@@ -362,3 +2112,132 @@ impl<'tcx> LateLintPass<'tcx> for RealtimeCallsNonrealtime {
 fn ui() {
     dylint_testing::ui_test(env!("CARGO_PKG_NAME"), "ui");
 }
+
+#[test]
+fn ui_strict() {
+    dylint_testing::ui::Test::src_base(env!("CARGO_PKG_NAME"), "ui_strict")
+        .dylint_toml(
+            r#"[mylints]
+strict = true
+strict_allow = [
+    "main::helpers::*",
+    "std::slice::*",
+    "std::option::*",
+    "std::fmt::*",
+    "std::io::error::*",
+    "std::sync::ReentrantLockGuard::*",
+]
+"#,
+        )
+        .run();
+}
+
+#[test]
+fn ui_closure_explicit() {
+    dylint_testing::ui::Test::src_base(env!("CARGO_PKG_NAME"), "ui_closure_explicit")
+        .dylint_toml("[mylints]\nclosure_policy = \"explicit\"\n")
+        .run();
+}
+
+#[test]
+fn ui_monomorphize() {
+    dylint_testing::ui::Test::src_base(env!("CARGO_PKG_NAME"), "ui_monomorphize")
+        .dylint_toml("[mylints]\nmonomorphize = true\n")
+        .run();
+}
+
+#[test]
+fn ui_external() {
+    dylint_testing::ui::Test::src_base(env!("CARGO_PKG_NAME"), "ui_external")
+        .dylint_toml(
+            r#"[mylints]
+external = [
+    { path = "std::time::Instant::now", class = "non_realtime", reason = "clock syscall" },
+    { path = "std::thread::sleep", class = "realtime" },
+    { path = "locks_lib::generic::Mutex::lock", class = "non_realtime", reason = "lock" },
+    { path = "locks_lib::flush", class = "non_realtime", reason = "io" },
+]
+"#,
+        )
+        .run();
+}
+
+/// The JSON report, SARIF log and inferred classifications of `ui_report`
+#[test]
+fn ui_report() {
+    let dir = std::env::temp_dir().join("mylints-ui-report");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let json = dir.join("report.jsonl");
+    let sarif = dir.join("report.sarif");
+    let inferred = dir.join("inferred.jsonl");
+    dylint_testing::ui::Test::src_base(env!("CARGO_PKG_NAME"), "ui_report")
+        .dylint_toml(format!(
+            "[mylints]\njson_report = {:?}\nsarif_report = {:?}\ninferred_dump = {:?}\n",
+            json, sarif, inferred
+        ))
+        .run();
+
+    let lines = |path: &std::path::Path| {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let violations = lines(&json);
+    let calls = violations
+        .iter()
+        .filter(|v| v["lint"] == "realtime_calls_nonrealtime")
+        .collect::<Vec<_>>();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0]["caller"], "process");
+    assert_eq!(calls[0]["callee"], "non_realtime_fn");
+    assert_eq!(calls[0]["kind"], "fn");
+    assert_eq!(calls[0]["reason"], "alloc");
+    assert_eq!(calls[0]["line"], 32);
+    assert_eq!(calls[1]["callee"], "non_realtime_fn");
+    assert_eq!(calls[1]["via"], serde_json::json!(["flush"]));
+    let opt_outs = violations
+        .iter()
+        .filter(|v| v["lint"] == "realtime_impl_violates_contract")
+        .collect::<Vec<_>>();
+    assert_eq!(opt_outs.len(), 1);
+    assert_eq!(opt_outs[0]["caller"], "<Meter as Probe>::read");
+    assert_eq!(opt_outs[0]["callee"], "Probe::read");
+    let summaries = violations
+        .iter()
+        .filter(|v| v["lint"] == "realtime_fn_not_realtime")
+        .collect::<Vec<_>>();
+    assert_eq!(summaries.len(), 1);
+    assert_eq!(summaries[0]["caller"], "process");
+
+    let log: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&sarif).unwrap()).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), violations.len());
+    assert_eq!(results[0]["ruleId"], "realtime_calls_nonrealtime");
+    // The UI test compiles an absolute path; relative ones resolve against SRCROOT
+    let artifact = &results[0]["locations"][0]["physicalLocation"]["artifactLocation"];
+    let uri = artifact["uri"].as_str().unwrap();
+    assert!(uri.starts_with("file:///") && uri.ends_with("/ui_report/main.rs"));
+    let root = log["runs"][0]["originalUriBaseIds"]["SRCROOT"]["uri"]
+        .as_str()
+        .unwrap();
+    assert!(root.starts_with("file:///") && root.ends_with('/'));
+
+    let statuses = lines(&inferred)
+        .iter()
+        .map(|entry| {
+            (
+                entry["function"].as_str().unwrap().to_string(),
+                entry["status"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert!(statuses.contains(&("gain".to_string(), "safe".to_string())));
+    assert!(statuses.contains(&("flush".to_string(), "unsafe".to_string())));
+    assert!(statuses.contains(&("discard".to_string(), "unsafe".to_string())));
+    assert!(statuses.contains(&("finish".to_string(), "unsafe".to_string())));
+}
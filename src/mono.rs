@@ -0,0 +1,124 @@
+// Monomorphization analysis: follow the MIR of a realtime function through the
+// concrete instances it calls, so that generic and trait calls (`T::process(&x)`,
+// `x.method()` with `x: T`) are checked against the function that actually runs
+
+use crate::Realtime;
+use rustc_hir::def_id::DefId;
+use rustc_middle::mir::TerminatorKind;
+use rustc_middle::ty::{
+    self, EarlyBinder, GenericArgKind, GenericArgsRef, Instance, TyCtxt, TypeVisitableExt,
+    TypingEnv,
+};
+use rustc_span::Span;
+use std::collections::HashSet;
+
+/// A call made by an instance: the function named in the source with its
+/// substituted generic arguments, and the instance it resolves to
+#[derive(Clone, Copy, Debug)]
+pub struct MonoCall<'tcx> {
+    pub callee: DefId,
+    pub args: GenericArgsRef<'tcx>,
+    pub resolved: Instance<'tcx>,
+    pub span: Span,
+}
+
+/// A non-realtime function reached from a realtime root through a call whose
+/// target depends on the generic substitution. The last call is the offending one.
+pub struct MonoFinding<'tcx> {
+    pub calls: Vec<MonoCall<'tcx>>,
+    pub rt: Realtime,
+}
+
+/// Walk the calls of `root` (a non-generic function) and of the unannotated
+/// local functions it reaches, with their generic arguments substituted.
+/// `classify` is the marker/configuration classification of a function.
+pub fn find_generic_violations<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    root: DefId,
+    classify: &dyn Fn(DefId) -> Option<Realtime>,
+) -> Vec<MonoFinding<'tcx>> {
+    let mut findings = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    walk(
+        tcx,
+        Instance::mono(tcx, root),
+        classify,
+        &mut visited,
+        &mut stack,
+        &mut findings,
+    );
+    findings
+}
+
+fn walk<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    instance: Instance<'tcx>,
+    classify: &dyn Fn(DefId) -> Option<Realtime>,
+    visited: &mut HashSet<Instance<'tcx>>,
+    stack: &mut Vec<MonoCall<'tcx>>,
+    findings: &mut Vec<MonoFinding<'tcx>>,
+) {
+    if !visited.insert(instance) || !tcx.is_mir_available(instance.def_id()) {
+        return;
+    }
+    let typing_env = TypingEnv::fully_monomorphized();
+    let body = tcx.optimized_mir(instance.def_id());
+    for block in body.basic_blocks.iter() {
+        let TerminatorKind::Call { func, fn_span, .. } = &block.terminator().kind else {
+            continue;
+        };
+        let Some((callee, args)) = func.const_fn_def() else {
+            continue;
+        };
+        // Calls whose arguments are concrete before the substitution are
+        // checked on HIR, or as implicit calls on MIR, already
+        let depends_on_substitution = args.has_param();
+        let args = instance.instantiate_mir_and_normalize_erasing_regions(
+            tcx,
+            typing_env,
+            EarlyBinder::bind(args),
+        );
+        // Trait objects are left to the dyn-dispatch check, shims have no
+        // source to blame
+        let Ok(Some(resolved)) = Instance::try_resolve(tcx, typing_env, callee, args) else {
+            continue;
+        };
+        if !matches!(resolved.def, ty::InstanceKind::Item(_)) {
+            continue;
+        }
+        let call = MonoCall {
+            callee,
+            args,
+            resolved,
+            span: fn_span.source_callsite(),
+        };
+        let target = resolved.def_id();
+        match classify(target) {
+            Some(rt @ Realtime::No(_)) if depends_on_substitution => {
+                let mut calls = stack.clone();
+                calls.push(call);
+                findings.push(MonoFinding { calls, rt });
+            }
+            // Look through unannotated local functions with their concrete arguments
+            None if target.is_local() => {
+                stack.push(call);
+                walk(tcx, resolved, classify, visited, stack, findings);
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The generic arguments of `def_id` by parameter name, e.g. "T = Rect, N = 4";
+/// lifetimes are left out
+pub fn substitution(tcx: TyCtxt<'_>, def_id: DefId, args: GenericArgsRef<'_>) -> String {
+    let generics = tcx.generics_of(def_id);
+    args.iter()
+        .enumerate()
+        .filter(|(_, arg)| !matches!(arg.unpack(), GenericArgKind::Lifetime(_)))
+        .map(|(i, arg)| format!("{} = {}", generics.param_at(i, tcx).name, arg))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,153 @@
+// Panic paths in realtime bodies, on MIR so that the bounds and overflow
+// checks inserted by the compiler are visible next to explicit panics
+
+use crate::knowledge::{self, inlined_call};
+use rustc_hir::def_id::{DefId, LocalDefId};
+use rustc_lint::LateContext;
+use rustc_middle::mir::{AssertKind, TerminatorKind};
+use rustc_middle::ty::{TyCtxt, TypingEnv};
+use rustc_span::{ExpnKind, MacroKind, Span};
+
+/// What may panic
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PanicKind {
+    /// `panic!`, `unreachable!`, `assert!`, `todo!`, ...
+    Explicit,
+    /// `unwrap` / `expect` and friends
+    Unwrap,
+    /// Indexing with a bounds check
+    BoundsCheck,
+    /// Arithmetic with an overflow check
+    OverflowCheck,
+    /// Division or remainder with a zero check
+    DivisionCheck,
+    /// Other compiler-inserted assertions
+    Assert,
+}
+
+impl PanicKind {
+    /// `count` sites of this kind, e.g. "2 bounds checks"
+    pub fn describe(self, count: usize) -> String {
+        let (one, many) = match self {
+            PanicKind::Explicit => ("explicit panic", "explicit panics"),
+            PanicKind::Unwrap => ("`unwrap`/`expect` call", "`unwrap`/`expect` calls"),
+            PanicKind::BoundsCheck => ("bounds check", "bounds checks"),
+            PanicKind::OverflowCheck => ("overflow check", "overflow checks"),
+            PanicKind::DivisionCheck => ("division by zero check", "division by zero checks"),
+            PanicKind::Assert => ("assertion", "assertions"),
+        };
+        format!("{} {}", count, if count == 1 { one } else { many })
+    }
+}
+
+/// A panic path found in a body: where it is (at the macro call site for code
+/// expanded from macros) and what panics
+pub struct PanicSite {
+    pub span: Span,
+    pub kind: PanicKind,
+    pub what: String,
+}
+
+/// Functions that panic, by canonical path or `prefix::*` pattern. The
+/// `*_failed` functions are what `unwrap` / `expect` call.
+static PANIC_FNS: &[(&str, PanicKind)] = &[
+    ("std::panicking::*", PanicKind::Explicit),
+    // `std::panic!` goes through the re-exports of `std::rt`
+    ("std::rt::begin_panic", PanicKind::Explicit),
+    ("std::rt::panic_fmt", PanicKind::Explicit),
+    ("std::rt::panic_display", PanicKind::Explicit),
+    ("std::rt::panic_explicit", PanicKind::Explicit),
+    ("std::option::Option::unwrap", PanicKind::Unwrap),
+    ("std::option::Option::expect", PanicKind::Unwrap),
+    ("std::option::unwrap_failed", PanicKind::Unwrap),
+    ("std::option::expect_failed", PanicKind::Unwrap),
+    ("std::result::Result::unwrap", PanicKind::Unwrap),
+    ("std::result::Result::expect", PanicKind::Unwrap),
+    ("std::result::Result::unwrap_err", PanicKind::Unwrap),
+    ("std::result::Result::expect_err", PanicKind::Unwrap),
+    ("std::result::unwrap_failed", PanicKind::Unwrap),
+];
+
+pub fn find_panics(cx: &LateContext<'_>, def_id: LocalDefId) -> Vec<PanicSite> {
+    let tcx = cx.tcx;
+    if !tcx.is_mir_available(def_id) {
+        return Vec::new();
+    }
+    let body = tcx.optimized_mir(def_id);
+    let typing_env = TypingEnv::post_analysis(tcx, def_id);
+    let mut found: Vec<PanicSite> = Vec::new();
+    let mut push = |span: Span, kind: PanicKind, what: String| {
+        let span = span.source_callsite();
+        // One report per source location, e.g. `assert!` both checks and panics
+        if !found.iter().any(|site| site.span == span) {
+            found.push(PanicSite { span, kind, what });
+        }
+    };
+
+    // Cleanup blocks run while unwinding from a panic found elsewhere
+    for block in body.basic_blocks.iter().filter(|block| !block.is_cleanup) {
+        let terminator = block.terminator();
+        // An inlined call is a panic path only if the callee is a panicking
+        // function itself
+        if let Some((instance, call_span)) = inlined_call(body, terminator.source_info.scope) {
+            if let Some((kind, path)) = panic_fn(tcx, instance.def_id()) {
+                push(call_span, kind, format!("`{}`", path));
+            }
+            continue;
+        }
+        match &terminator.kind {
+            // 1) Checks inserted by the compiler
+            TerminatorKind::Assert { msg, .. } => {
+                let (kind, what) = match &**msg {
+                    AssertKind::BoundsCheck { .. } => (PanicKind::BoundsCheck, "bounds check"),
+                    AssertKind::Overflow(..) | AssertKind::OverflowNeg(..) => {
+                        (PanicKind::OverflowCheck, "arithmetic overflow check")
+                    }
+                    AssertKind::DivisionByZero(..) | AssertKind::RemainderByZero(..) => {
+                        (PanicKind::DivisionCheck, "division by zero check")
+                    }
+                    _ => (PanicKind::Assert, "assertion"),
+                };
+                push(terminator.source_info.span, kind, what.to_string());
+            }
+            // 2) Calls of panicking functions
+            TerminatorKind::Call { func, fn_span, .. } => {
+                let Some((callee, args)) = func.const_fn_def() else {
+                    continue;
+                };
+                let callee = knowledge::resolve_callee(tcx, typing_env, callee, args);
+                let Some((kind, path)) = panic_fn(tcx, callee) else {
+                    continue;
+                };
+                // Name explicit panics after the macro they come from
+                let what = match macro_name(*fn_span) {
+                    Some(name) if kind == PanicKind::Explicit => format!("`{}!`", name),
+                    _ => format!("`{}`", path),
+                };
+                push(*fn_span, kind, what);
+            }
+            _ => {}
+        }
+    }
+    found.sort_by_key(|site| site.span.lo());
+    found
+}
+
+/// Kind of panic `callee` is, with its canonical path, if it panics
+fn panic_fn(tcx: TyCtxt<'_>, callee: DefId) -> Option<(PanicKind, String)> {
+    let path = knowledge::canonical_path(tcx, callee);
+    let (_, kind) = PANIC_FNS
+        .iter()
+        .find(|(pattern, _)| knowledge::path_matches(pattern, &path))?;
+    Some((*kind, path))
+}
+
+/// Outermost bang macro `span` was expanded from
+fn macro_name(span: Span) -> Option<String> {
+    span.macro_backtrace()
+        .filter_map(|expn| match expn.kind {
+            ExpnKind::Macro(MacroKind::Bang, name) => Some(name.to_string()),
+            _ => None,
+        })
+        .last()
+}
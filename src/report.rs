@@ -0,0 +1,266 @@
+// Machine-readable reports of the violations, written alongside the normal
+// diagnostics: JSON lines (`RT_LINT_JSON` / `json_report`) and a SARIF 2.1.0
+// log (`RT_LINT_SARIF` / `sarif_report`)
+
+use crate::config::Config;
+use rustc_lint::{Level, Lint};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Environment variable naming the JSON-lines report file, takes precedence
+/// over the `json_report` key of dylint.toml
+pub const JSON_REPORT_ENV: &str = "RT_LINT_JSON";
+/// Environment variable naming the SARIF log file, takes precedence over the
+/// `sarif_report` key of dylint.toml
+pub const SARIF_REPORT_ENV: &str = "RT_LINT_SARIF";
+/// SARIF base id of the relative paths, the directory rustc runs in (the
+/// workspace root under cargo)
+const SARIF_SRCROOT: &str = "SRCROOT";
+
+/// How the realtime function reaches the offending callee
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallKind {
+    Method,
+    Fn,
+    Closure,
+    FnPtr,
+    /// Drop glue run when a value goes out of scope
+    Drop,
+    /// Call inserted by the compiler: overloaded operators, dereferences,
+    /// indexing, `?` and `for` loops
+    Implicit,
+}
+
+/// A source position
+#[derive(Clone, Debug, Serialize)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+}
+
+/// One call of the chain leading to a violation
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub message: String,
+    pub location: Location,
+}
+
+/// One reported violation
+#[derive(Clone, Debug, Serialize)]
+pub struct Violation {
+    /// Name of the lint that fired
+    pub lint: String,
+    /// SARIF level of the emitted diagnostic (`warning` or `error`)
+    #[serde(skip)]
+    pub level: &'static str,
+    pub message: String,
+    /// Realtime function the violation was found in, or the function whose
+    /// marker is violated
+    pub caller: String,
+    /// Offending function (or the variable name, for closures and fn pointers)
+    pub callee: String,
+    pub kind: CallKind,
+    pub reason: Option<String>,
+    /// Unannotated functions between the caller and the callee, for transitive violations
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub via: Vec<String>,
+    #[serde(flatten)]
+    pub location: Location,
+    /// Every call from the caller to the callee, the first one being at `location`
+    #[serde(skip)]
+    pub flow: Vec<Step>,
+}
+
+/// SARIF level of a lint level
+pub fn sarif_level(level: Level) -> &'static str {
+    match level {
+        Level::Deny | Level::Forbid => "error",
+        _ => "warning",
+    }
+}
+
+/// Report sinks, each disabled unless configured
+#[derive(Default)]
+pub struct Report {
+    /// JSON-lines file. Lines are appended, so the reports of all crates of a
+    /// build end up in the same file.
+    json: Option<File>,
+    /// SARIF log path, the log is written once the crate has been checked
+    sarif: Option<String>,
+    violations: RefCell<Vec<Violation>>,
+}
+
+impl Report {
+    pub fn open(config: &Config) -> Self {
+        let json = setting(JSON_REPORT_ENV, config.json_report.as_deref()).and_then(|path| {
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    eprintln!("[dylint] cannot open JSON report {}: {}", path, err);
+                    None
+                }
+            }
+        });
+        Self {
+            json,
+            sarif: setting(SARIF_REPORT_ENV, config.sarif_report.as_deref()),
+            violations: RefCell::default(),
+        }
+    }
+
+    pub fn record(&self, violation: Violation) {
+        // `&File` is `Write`, so the pass can report from `&self` methods
+        if let Some(mut file) = self.json.as_ref() {
+            // One write per line, so that lines of crates compiled in parallel
+            // into the same append-mode file do not interleave
+            let mut line = serde_json::to_string(&violation).expect("violation serializes");
+            line.push('\n');
+            if let Err(err) = file.write_all(line.as_bytes()) {
+                eprintln!("[dylint] cannot write JSON report: {}", err);
+            }
+        }
+        if self.sarif.is_some() {
+            self.violations.borrow_mut().push(violation);
+        }
+    }
+
+    /// Write the SARIF log of the crate, with one rule per lint of `rules`.
+    /// `{crate}` in the configured path is replaced by the crate name, so that
+    /// the crates of a workspace do not overwrite each other's log.
+    pub fn finish(&self, crate_name: &str, rules: &[&'static Lint]) {
+        let Some(path) = &self.sarif else {
+            return;
+        };
+        let path = path.replace("{crate}", crate_name);
+        let log = sarif_log(rules, &self.violations.borrow());
+        let written = File::create(&path).and_then(|mut file| {
+            serde_json::to_writer_pretty(&mut file, &log)?;
+            writeln!(file)
+        });
+        match written {
+            Ok(()) => eprintln_ts!("[dylint] SARIF log written to {}", path),
+            Err(err) => eprintln!("[dylint] cannot write SARIF log {}: {}", path, err),
+        }
+    }
+}
+
+/// Path configured through `env` or else through dylint.toml
+pub fn setting(env: &str, configured: Option<&str>) -> Option<String> {
+    std::env::var(env)
+        .ok()
+        .or_else(|| configured.map(str::to_string))
+}
+
+/// `path` as a URI reference: `/`-separated, with the characters URIs
+/// reserve percent-encoded
+fn uri_path(path: &str) -> String {
+    path.replace('\\', "/")
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~/:@!$&'()*+,;=".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+/// `file://` URI of an absolute path
+fn file_uri(path: &Path) -> String {
+    let path = uri_path(&path.to_string_lossy());
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        // `C:/...` on Windows
+        format!("file:///{}", path)
+    }
+}
+
+/// Relative paths are resolved against `SARIF_SRCROOT`, absolute ones (other
+/// crates, the standard library) are `file://` URIs
+fn sarif_artifact(file: &str) -> Value {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        json!({ "uri": file_uri(path) })
+    } else {
+        json!({ "uri": uri_path(file), "uriBaseId": SARIF_SRCROOT })
+    }
+}
+
+fn sarif_location(location: &Location) -> Value {
+    json!({
+        "physicalLocation": {
+            "artifactLocation": sarif_artifact(&location.file),
+            "region": { "startLine": location.line, "startColumn": location.column },
+        }
+    })
+}
+
+fn sarif_log(rules: &[&'static Lint], violations: &[Violation]) -> Value {
+    let rule_ids = rules
+        .iter()
+        .map(|lint| lint.name_lower())
+        .collect::<Vec<_>>();
+    let sarif_rules = rules
+        .iter()
+        .zip(&rule_ids)
+        .map(|(lint, id)| {
+            json!({
+                "id": id,
+                "shortDescription": { "text": lint.desc },
+                "defaultConfiguration": { "level": sarif_level(lint.default_level) },
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = violations
+        .iter()
+        .map(|violation| {
+            // The call chain, from the call in the realtime function to the offending call
+            let flow = violation
+                .flow
+                .iter()
+                .map(|step| {
+                    let mut location = sarif_location(&step.location);
+                    location["message"] = json!({ "text": step.message });
+                    json!({ "location": location })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "ruleId": violation.lint,
+                "ruleIndex": rule_ids.iter().position(|id| *id == violation.lint),
+                "level": violation.level,
+                "message": { "text": violation.message },
+                "locations": [sarif_location(&violation.location)],
+                "codeFlows": [{ "threadFlows": [{ "locations": flow }] }],
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut run = json!({
+        "tool": {
+            "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "rules": sarif_rules,
+            },
+        },
+        "results": results,
+    });
+    if let Ok(root) = std::env::current_dir() {
+        // The base URI of a directory ends with a slash
+        let root = format!("{}/", file_uri(&root).trim_end_matches('/'));
+        run["originalUriBaseIds"] = json!({ SARIF_SRCROOT: { "uri": root } });
+    }
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [run],
+    })
+}
@@ -0,0 +1,77 @@
+// Dyn dispatch, fn pointer and closure calls checked by the lints
+
+// Nightly: attributes on statements/expressions & proc-macro hygiene adjustments
+#![feature(stmt_expr_attributes)]
+#![feature(proc_macro_hygiene)]
+
+use std::f64::consts::PI;
+use rt_attrs::{non_realtime, realtime, rt_call_info};
+
+// ---- Trait & types ----
+trait Shape {
+    fn name(&self) -> &'static str;
+    fn area(&self) -> f64;
+}
+
+struct Circle { r: f64 }
+struct Rect { w: f64, h: f64 }
+
+impl Shape for Circle {
+    fn name(&self) -> &'static str { "Circle" }
+    #[realtime]
+    fn area(&self) -> f64 { PI * self.r * self.r }
+}
+impl Shape for Rect {
+    fn name(&self) -> &'static str { "Rect" }
+    #[non_realtime("io")]
+    fn area(&self) -> f64 {
+        println!("(Rect) computing area");
+        self.w * self.h
+    }
+}
+
+// ========== trait dispatch (dynamic) ==========
+// `s.area()` may run `<Circle as Shape>::area` (realtime) or
+// `<Rect as Shape>::area` (non-realtime), so the call is reported.
+// `s.name()` is unannotated in both impls and reaches nothing non-realtime.
+#[realtime]
+fn dyn_area(s: &dyn Shape) -> f64 {
+    let _ = s.name();
+    s.area()
+}
+
+// Function pointer target
+#[non_realtime("io")]
+fn via_fn_ptr(s: &dyn Shape) -> f64 {
+    println!("(fn ptr) {}", s.name());
+    s.area()
+}
+
+// ========== function pointer call ==========
+// `fp` holds the non-realtime `via_fn_ptr`, so the call through `fp` is
+// reported.
+#[realtime]
+fn fn_ptr_area(s: &dyn Shape) -> f64 {
+    let fp: fn(&dyn Shape) -> f64 = via_fn_ptr; // function pointer
+    fp(s)
+}
+
+fn main() {
+    let mut shapes: Vec<Box<dyn Shape>> = Vec::new();
+    shapes.push(Box::new(Circle { r: 2.0 }));
+    shapes.push(Box::new(Rect { w: 3.0, h: 4.0 }));
+
+    for s in &shapes {
+        println!("(dyn dispatch) {} area = {:.2}", s.name(), dyn_area(s.as_ref()));
+        println!("(fn ptr) area = {:.2}", fn_ptr_area(s.as_ref()));
+    }
+
+    // ========== closure call ==========
+    // The closure is realtime by its marker: its `println!` and the dyn call
+    // of `area` are reported in its body.
+    #[rt_call_info("closure", "realtime")]
+    let print_with_closure = |shape: &dyn Shape| {
+        println!("(closure) {} area = {:.2}", shape.name(), shape.area());
+    };
+    print_with_closure(shapes[0].as_ref());
+}
@@ -2,11 +2,11 @@
 use rt_attrs::realtime;
 use my_functions_lib::{do_rt, do_slow, realtime_d, non_realtime_d, realtime_e, non_realtime_e};
 
-// #[realtime]
-// fn audio() {
-//     do_rt();    // OK - realtime function calling a realtime function
-//     do_slow();  // ❌ Dylint should warn: realtime function calling a non-realtime function
-// }
+#[realtime]
+fn audio() {
+    do_rt();    // OK - realtime function calling a realtime function
+    do_slow();  // ❌ Dylint should warn: realtime function calling a non-realtime function
+}
 
 #[realtime]
 fn A()
@@ -0,0 +1,41 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+#[doc = "rt:realtime"]
+fn process(buffer: &mut Vec<f32>, name: &str) -> usize {
+    let scratch = vec![0.0f32; 16];
+    let boxed = Box::new(1u8);
+    let label = format!("{}", name);
+    buffer.push(scratch[0] + *boxed as f32);
+    label.len()
+}
+
+#[doc = "rt:realtime"]
+fn in_place(buffer: &mut [f32]) {
+    for sample in buffer.iter_mut() {
+        *sample *= 0.5;
+    }
+}
+
+// The allocation is not reported, but still counted against the marker
+#[allow(realtime_allocates)]
+#[doc = "rt:realtime"]
+fn scratch_len(n: usize) -> usize {
+    vec![0u8; n].len()
+}
+
+// Unannotated, allocates
+fn helper() {
+    let _ = vec![0u8; 4];
+}
+
+// Unannotated, frees the buffer it is given
+fn release(_buffer: Vec<f32>) {}
+
+#[doc = "rt:realtime"]
+fn through_helpers(buffer: Vec<f32>) {
+    helper();
+    release(buffer);
+}
+
+fn main() {}
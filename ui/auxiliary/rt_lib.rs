@@ -0,0 +1,9 @@
+#![crate_type = "lib"]
+
+#[doc = "rt:realtime"]
+pub fn fast() {}
+
+#[doc = "rt:non_realtime"]
+pub fn slow() {}
+
+pub fn unmarked() {}
@@ -0,0 +1,22 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+// Unmarked std functions are classified by the built-in table
+#[doc = "rt:realtime"]
+fn process(commands: &Receiver<u32>) -> u32 {
+    std::thread::sleep(Duration::from_millis(1));
+    let command = commands.recv().unwrap_or(0);
+    println!("command {}", command);
+    command
+}
+
+// Std functions missing from the table stay unmarked
+#[doc = "rt:realtime"]
+fn poll(commands: &Receiver<u32>) -> Option<u32> {
+    commands.try_recv().ok()
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+#[doc = "rt:non_realtime:waits for the worker"]
+fn wait_for_worker() {}
+
+// Two unannotated hops between the realtime function and the violation
+fn drain() {
+    wait_for_worker();
+}
+
+fn flush() {
+    drain();
+}
+
+fn settle() {
+    std::thread::sleep(Duration::from_millis(1));
+}
+
+#[doc = "rt:realtime"]
+fn process() {
+    flush();
+    settle();
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code, unused_doc_comments)]
+
+#[doc = "rt:non_realtime"]
+fn non_realtime_fn() {}
+
+#[doc = "rt:realtime"]
+fn for_each_sample(buffer: &mut [f32], f: impl Fn(&mut f32)) {
+    for sample in buffer.iter_mut() {
+        f(sample);
+    }
+}
+
+#[doc = "rt:realtime"]
+fn process(_buffer: &mut [f32]) {
+    // Defined in realtime code: inherits its context
+    let scale = |x: f32| {
+        non_realtime_fn();
+        x * 0.5
+    };
+    let _ = scale(1.0);
+    let label = |x: f32| format!("{}", x).len();
+    let _ = label(1.0);
+    #[doc = "rt:call-info:closure:nonrealtime"]
+    let slow = || 1.0f32;
+    let _ = slow();
+}
+
+// Passed to a realtime function: checked as realtime code
+fn setup(buffer: &mut [f32]) {
+    for_each_sample(buffer, |sample| {
+        non_realtime_fn();
+        *sample = 0.0;
+    });
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+struct Logger;
+
+impl Drop for Logger {
+    #[doc = "rt:non_realtime:io"]
+    fn drop(&mut self) {}
+}
+
+struct Quiet(u32);
+
+impl Drop for Quiet {
+    fn drop(&mut self) {
+        self.0 = 0;
+    }
+}
+
+#[doc = "rt:realtime"]
+fn process(samples: Vec<f32>, logger: Logger, quiet: Quiet, out: &mut Vec<f32>) -> usize {
+    let n = samples.len();
+    drop(logger);
+    let _ = quiet;
+    out.clear();
+    n
+}
+
+fn main() {}
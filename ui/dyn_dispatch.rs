@@ -0,0 +1,81 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+trait Shape {
+    fn area(&self) -> f64;
+}
+
+#[doc = "rt:non_realtime"]
+fn log_area() {}
+
+struct Circle(f64);
+
+impl Shape for Circle {
+    #[doc = "rt:realtime"]
+    fn area(&self) -> f64 {
+        3.14 * self.0 * self.0
+    }
+}
+
+struct Rect(f64, f64);
+
+impl Shape for Rect {
+    fn area(&self) -> f64 {
+        log_area();
+        self.0 * self.1
+    }
+}
+
+#[doc = "rt:realtime"]
+fn total_area(shapes: &[&dyn Shape]) -> f64 {
+    let mut total = 0.0;
+    for shape in shapes {
+        total += shape.area();
+    }
+    total
+}
+
+trait Sink {
+    fn emit(&self, value: f64);
+}
+
+struct Console;
+
+impl Sink for Console {
+    fn emit(&self, value: f64) {
+        println!("{}", value);
+    }
+}
+
+#[doc = "rt:realtime"]
+fn report(sink: &dyn Sink, value: f64) {
+    sink.emit(value);
+}
+
+trait Store {
+    fn put(&self, value: f64);
+}
+
+struct Logged;
+
+impl Store for Logged {
+    fn put(&self, value: f64) {
+        println!("{}", value);
+    }
+}
+
+struct Shared(std::sync::Mutex<f64>);
+
+impl Store for Shared {
+    fn put(&self, value: f64) {
+        *self.0.lock().unwrap() = value;
+    }
+}
+
+// One place, counted as both an I/O call and a blocking lock
+#[doc = "rt:realtime"]
+fn store(target: &dyn Store, value: f64) {
+    target.put(value);
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+// aux-build:rt_lib.rs
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+extern crate rt_lib;
+
+// Markers of functions from other crates are read from their metadata
+#[doc = "rt:realtime"]
+fn process() {
+    rt_lib::fast();
+    rt_lib::slow();
+    rt_lib::unmarked();
+}
+
+fn main() {}
@@ -0,0 +1,40 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+#[doc = "rt:realtime"]
+fn fast() {}
+
+#[doc = "rt:non_realtime"]
+fn slow() {}
+
+struct Callbacks {
+    on_block: fn(),
+}
+
+static TABLE: [fn(); 2] = [fast, slow];
+
+#[doc = "rt:realtime"]
+fn process(callbacks: &Callbacks, i: usize, flag: bool) {
+    (callbacks.on_block)();
+    TABLE[i]();
+    let mut f: fn() = fast;
+    f();
+    if flag {
+        f = slow;
+    }
+    f();
+    let g: fn() = fast;
+    {
+        // Another binding of the same name: does not change `g` above
+        let g: fn() = slow;
+        let _ = g;
+    }
+    g();
+    let open: fn() -> std::io::Stdout = std::io::stdout;
+    open();
+}
+
+fn main() {
+    let callbacks = Callbacks { on_block: slow };
+    process(&callbacks, 0, true);
+}
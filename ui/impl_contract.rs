@@ -0,0 +1,43 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+trait AudioProcessor {
+    #[doc = "rt:realtime"]
+    fn process_audio(&self, buffer: &mut [f32]);
+}
+
+#[doc = "rt:non_realtime"]
+fn log_block() {}
+
+struct SimpleProcessor;
+
+// Unmarked: held to the contract of the trait method
+impl AudioProcessor for SimpleProcessor {
+    fn process_audio(&self, buffer: &mut [f32]) {
+        log_block();
+        println!("processing {} samples", buffer.len());
+        for sample in buffer.iter_mut() {
+            *sample *= 0.5;
+        }
+    }
+}
+
+struct LoggingProcessor;
+
+// Marked non-realtime: cannot opt out of the contract
+impl AudioProcessor for LoggingProcessor {
+    #[doc = "rt:non_realtime"]
+    fn process_audio(&self, _buffer: &mut [f32]) {}
+}
+
+struct SilentProcessor;
+
+impl AudioProcessor for SilentProcessor {
+    fn process_audio(&self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = 0.0;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,68 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::ops::{Add, Deref, Index};
+
+#[derive(Clone, Copy)]
+struct Sample(f32);
+
+impl Add for Sample {
+    type Output = Sample;
+    #[doc = "rt:non_realtime:io"]
+    fn add(self, other: Sample) -> Sample {
+        Sample(self.0 + other.0)
+    }
+}
+
+struct Table([f32; 4]);
+
+impl Index<usize> for Table {
+    type Output = f32;
+    #[doc = "rt:non_realtime:lock"]
+    fn index(&self, i: usize) -> &f32 {
+        &self.0[i % 4]
+    }
+}
+
+struct Guard(Vec<f32>);
+
+impl Deref for Guard {
+    type Target = [f32];
+    #[doc = "rt:non_realtime:lock"]
+    fn deref(&self) -> &[f32] {
+        &self.0
+    }
+}
+
+#[derive(Debug)]
+struct ParseError;
+
+impl From<std::num::ParseIntError> for ParseError {
+    #[doc = "rt:non_realtime:alloc"]
+    fn from(_: std::num::ParseIntError) -> Self {
+        ParseError
+    }
+}
+
+struct Voices;
+
+impl IntoIterator for Voices {
+    type Item = u8;
+    type IntoIter = std::ops::Range<u8>;
+    #[doc = "rt:non_realtime:alloc"]
+    fn into_iter(self) -> Self::IntoIter {
+        0..4
+    }
+}
+
+#[doc = "rt:realtime"]
+fn process(a: Sample, b: Sample, t: &Table, g: &Guard, s: &str, v: Voices) -> Result<f32, ParseError> {
+    let c = a + b;
+    let x = t[1];
+    let n = g.len();
+    let p: u32 = s.parse::<u32>()?;
+    for _ in v {}
+    Ok(c.0 + x + n as f32 + p as f32)
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::io::Write;
+
+#[doc = "rt:realtime"]
+fn process(level: f32) {
+    println!("level {}", level);
+    let _ = std::fs::read("settings.toml");
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{}", level);
+}
+
+// Unannotated, does I/O
+fn trace(level: f32) {
+    eprintln!("{}", level);
+}
+
+#[doc = "rt:realtime"]
+fn traced(level: f32) {
+    trace(level);
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::sync::{Mutex, RwLock};
+
+struct State {
+    gain: f32,
+}
+
+#[doc = "rt:realtime"]
+fn process(state: &Mutex<State>, table: &RwLock<Vec<f32>>) -> f32 {
+    let gain = state.lock().unwrap().gain;
+    let first = table.read().unwrap()[0];
+    gain * first
+}
+
+#[doc = "rt:realtime"]
+fn try_process(state: &Mutex<State>) -> f32 {
+    // Does not block
+    match state.try_lock() {
+        Ok(state) => state.gain,
+        Err(_) => 0.0,
+    }
+}
+
+fn main() {}
@@ -1 +1,41 @@
-fn main() {}
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+#[doc = "rt:non_realtime"]
+fn non_realtime_fn() {}
+
+#[doc = "rt:non_realtime:blocking"]
+fn wait_for_worker() {}
+
+#[doc = "rt:realtime"]
+fn realtime_helper(x: f32) -> f32 {
+    x * 0.5
+}
+
+// Unannotated, does only realtime work: inferred safe
+fn gain(x: f32) -> f32 {
+    realtime_helper(x) * 2.0
+}
+
+// Unannotated, reaches a non-realtime function
+fn flush() {
+    wait_for_worker();
+}
+
+#[doc = "rt:realtime"]
+fn process(x: f32) -> f32 {
+    non_realtime_fn();
+    flush();
+    gain(x) + realtime_helper(x)
+}
+
+// Not realtime: may call anything
+fn setup() {
+    non_realtime_fn();
+    flush();
+}
+
+fn main() {
+    setup();
+    process(1.0);
+}
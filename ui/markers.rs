@@ -0,0 +1,23 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![feature(register_tool)]
+#![register_tool(rt)]
+#![allow(dead_code)]
+
+#[doc = "rt:non_realtime:alloc"]
+fn doc_marked() {}
+
+#[rt::non_realtime = "io"]
+fn tool_marked() {}
+
+// Not a marker: doc markers must be the whole doc string
+/// rt:non_realtime
+fn doc_comment() {}
+
+#[rt::realtime]
+fn process() {
+    doc_marked();
+    tool_marked();
+    doc_comment();
+}
+
+fn main() {}
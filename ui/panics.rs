@@ -0,0 +1,19 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+#![warn(realtime_panics)]
+
+#[doc = "rt:realtime"]
+fn process(buffer: &mut [f32], gain: Option<f32>, i: usize) -> f32 {
+    let gain = gain.unwrap();
+    if i > buffer.len() {
+        panic!("out of range");
+    }
+    buffer[i] * gain
+}
+
+#[doc = "rt:realtime"]
+fn checked(buffer: &[f32], i: usize) -> f32 {
+    buffer.get(i).copied().unwrap_or(0.0)
+}
+
+fn main() {}
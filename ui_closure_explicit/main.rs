@@ -0,0 +1,35 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code, unused_doc_comments)]
+
+#[doc = "rt:non_realtime"]
+fn non_realtime_fn() {}
+
+#[doc = "rt:realtime"]
+fn for_each_sample(buffer: &mut [f32], f: impl Fn(&mut f32)) {
+    for sample in buffer.iter_mut() {
+        f(sample);
+    }
+}
+
+#[doc = "rt:realtime"]
+fn process(buffer: &mut [f32]) {
+    // Not realtime under the explicit policy: checked through what they do
+    let reset = || non_realtime_fn();
+    reset();
+    let label = |x: f32| format!("{}", x).len();
+    let _ = label(1.0);
+    // Checked: marked realtime
+    #[doc = "rt:call-info:closure:realtime"]
+    let scale = |x: f32| {
+        non_realtime_fn();
+        x * 0.5
+    };
+    let _ = scale(1.0);
+    // Checked: passed to a realtime function
+    for_each_sample(buffer, |sample| {
+        non_realtime_fn();
+        *sample = 0.0;
+    });
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+#![crate_type = "lib"]
+
+// Public lock types are aliases of generic ones, as in parking_lot, whose
+// `Mutex<T>` is `lock_api::Mutex<RawMutex, T>`
+pub mod generic {
+    pub struct Mutex<R, T> {
+        _raw: R,
+        value: T,
+    }
+
+    impl<R, T> Mutex<R, T> {
+        pub fn new(raw: R, value: T) -> Self {
+            Self { _raw: raw, value }
+        }
+
+        pub fn lock(&self) -> &T {
+            &self.value
+        }
+    }
+}
+
+pub struct RawMutex;
+
+pub type Mutex<T> = generic::Mutex<RawMutex, T>;
+
+mod imp {
+    pub fn flush() {}
+}
+
+// Named by its public path
+pub use imp::flush;
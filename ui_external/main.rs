@@ -0,0 +1,26 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+// aux-build:locks_lib.rs
+#![allow(dead_code)]
+
+extern crate locks_lib;
+
+use std::time::{Duration, Instant};
+
+#[doc = "rt:realtime"]
+fn process(deadline: Instant) -> bool {
+    // Classified non-realtime by an `external` rule
+    let now = Instant::now();
+    // Classified realtime by an `external` rule, over the built-in table
+    std::thread::sleep(Duration::from_micros(1));
+    now < deadline
+}
+
+// `locks_lib::Mutex` is an alias: rules name the type it stands for
+#[doc = "rt:realtime"]
+fn read(state: &locks_lib::Mutex<f32>) -> f32 {
+    let value = *state.lock();
+    locks_lib::flush();
+    value
+}
+
+fn main() {}
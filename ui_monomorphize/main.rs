@@ -0,0 +1,53 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+use std::ops::Add;
+
+trait Processor {
+    fn process(&self) -> f32;
+}
+
+struct Quiet;
+
+impl Processor for Quiet {
+    #[doc = "rt:realtime"]
+    fn process(&self) -> f32 {
+        0.0
+    }
+}
+
+struct Noisy;
+
+impl Processor for Noisy {
+    #[doc = "rt:non_realtime:io"]
+    fn process(&self) -> f32 {
+        1.0
+    }
+}
+
+// Unannotated and generic: what it calls depends on `T`
+fn run<T: Processor>(p: &T) -> f32 {
+    p.process()
+}
+
+#[derive(Clone, Copy)]
+struct Sample(f32);
+
+impl Add for Sample {
+    type Output = Sample;
+    #[doc = "rt:non_realtime"]
+    fn add(self, other: Sample) -> Sample {
+        Sample(self.0 + other.0)
+    }
+}
+
+// Each call is reported once
+#[doc = "rt:realtime"]
+fn process(quiet: &Quiet, noisy: &Noisy, a: Sample, b: Sample) -> f32 {
+    let generic = run(quiet) + run(noisy);
+    let concrete = noisy.process();
+    let operator = (a + b).0;
+    generic + concrete + operator
+}
+
+fn main() {}
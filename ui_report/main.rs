@@ -0,0 +1,52 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+
+#[doc = "rt:non_realtime:alloc"]
+fn non_realtime_fn() {}
+
+// Unannotated, inferred safe
+fn gain(x: f32) -> f32 {
+    x * 2.0
+}
+
+// Unannotated, inferred unsafe
+fn flush() {
+    non_realtime_fn();
+}
+
+// Unannotated, inferred unsafe through its drop glue
+fn discard(_buffer: Vec<f32>) {}
+
+struct Logger;
+
+impl Drop for Logger {
+    #[doc = "rt:non_realtime:io"]
+    fn drop(&mut self) {}
+}
+
+// Unannotated, inferred unsafe through the destructor it runs
+fn finish(_logger: Logger) {}
+
+#[doc = "rt:realtime"]
+fn process(x: f32) -> f32 {
+    non_realtime_fn();
+    flush();
+    gain(x)
+}
+
+trait Probe {
+    #[doc = "rt:realtime"]
+    fn read(&self) -> f32;
+}
+
+struct Meter;
+
+// Opts out of the realtime contract
+impl Probe for Meter {
+    #[doc = "rt:non_realtime"]
+    fn read(&self) -> f32 {
+        0.0
+    }
+}
+
+fn main() {}
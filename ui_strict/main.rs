@@ -0,0 +1,60 @@
+// normalize-stderr-test "\[ *[0-9]+\.[0-9]{3}s\] " -> ""
+#![allow(dead_code)]
+#![warn(realtime_calls_unannotated)]
+
+use std::io::Write;
+
+mod helpers {
+    // Allowed by `strict_allow`
+    pub fn clamp(x: f32) -> f32 {
+        if x > 1.0 {
+            1.0
+        } else {
+            x
+        }
+    }
+}
+
+// Unannotated, even though it only does realtime work
+fn double(x: f32) -> f32 {
+    x * 2.0
+}
+
+#[doc = "rt:realtime"]
+fn halve(x: f32) -> f32 {
+    x * 0.5
+}
+
+trait Filter {
+    fn apply(&self, x: f32) -> f32;
+}
+
+struct LowPass;
+
+impl Filter for LowPass {
+    fn apply(&self, x: f32) -> f32 {
+        x * 0.9
+    }
+}
+
+#[doc = "rt:realtime"]
+fn process(x: f32, samples: &[f32], filter: &dyn Filter, f: fn(f32) -> f32) -> f32 {
+    let a = double(x);
+    let b = helpers::clamp(x);
+    let c = samples.len() as f32;
+    let d = halve(x);
+    let e = filter.apply(x);
+    let g = f(x);
+    let h = Some(1.0f32);
+    a + b + c + d + e + g + h.unwrap_or(0.0)
+}
+
+#[doc = "rt:realtime"]
+fn report(level: f32) {
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "{}", level);
+}
+
+fn main() {
+    process(1.0, &[], &LowPass, double);
+}